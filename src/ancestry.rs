@@ -0,0 +1,171 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Structural queries: depths, paths and common ancestors of values
+//
+//
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Direction, Summary, BST};
+
+// Searches the subtree under `node` for `val`, reporting every step taken.
+// Returns false if the value is not there.
+fn search_steps<V: Ord, S, F: FnMut(Direction)>(
+    node: &BSTNode<V, S>,
+    val: &V,
+    mut step: F,
+) -> bool {
+    let mut node = node;
+    loop {
+        let dir = match node.val.as_ref().map(|v| val.cmp(v)) {
+            Some(Ordering::Equal) => return true,
+            Some(Ordering::Less) => Direction::Left,
+            Some(Ordering::Greater) => Direction::Right,
+            None => return false,
+        };
+        step(dir);
+        let next = match dir {
+            Direction::Left => &node.left,
+            Direction::Right => &node.right,
+        };
+        node = match *next {
+            Empty => return false,
+            NonEmpty(ref n) => n,
+        };
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Returns the depth of the node holding `val`, counted in nodes like `height`,
+    /// so the root is at depth 1.
+    ///
+    pub fn depth_of(&self, val: &V) -> Option<usize> {
+        let root = match *self {
+            Empty => return None,
+            NonEmpty(ref n) => n,
+        };
+        let mut depth = 1;
+        if search_steps(root, val, |_| depth += 1) {
+            Some(depth)
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Returns the left/right steps leading from the root to `val`; empty for the root.
+    ///
+    pub fn path_to(&self, val: &V) -> Option<Vec<Direction>> {
+        let root = match *self {
+            Empty => return None,
+            NonEmpty(ref n) => n,
+        };
+        let mut path = Vec::new();
+        if search_steps(root, val, |d| path.push(d)) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    // The deepest node whose subtree holds both values, if both are in the tree
+    fn split_node(&self, a: &V, b: &V) -> Option<&BSTNode<V, S>> {
+        let mut tree = self;
+        while let NonEmpty(ref n) = *tree {
+            let v = n.val.as_ref()?;
+            if a < v && b < v {
+                tree = &n.left;
+            } else if a > v && b > v {
+                tree = &n.right;
+            } else {
+                // The values part ways here, so this is the ancestor if both exist
+                return if search_steps(n, a, |_| ()) && search_steps(n, b, |_| ()) {
+                    Some(n)
+                } else {
+                    None
+                };
+            }
+        }
+        None
+    }
+
+    ///
+    /// Returns the value of the deepest node that has both `a` and `b` in its
+    /// subtree, a node counting as its own descendant. None unless both are present.
+    ///
+    /// Uses the ordering: walks down while both values lie on the same side.
+    ///
+    pub fn lowest_common_ancestor(&self, a: &V, b: &V) -> Option<&V> {
+        self.split_node(a, b)?.val.as_ref()
+    }
+
+    ///
+    /// Returns the number of edges on the path between the nodes holding `a` and `b`.
+    ///
+    pub fn distance(&self, a: &V, b: &V) -> Option<usize> {
+        let split = self.split_node(a, b)?;
+        let (mut from_a, mut from_b) = (0, 0);
+        search_steps(split, a, |_| from_a += 1);
+        search_steps(split, b, |_| from_b += 1);
+        Some(from_a + from_b)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn depth_and_path_of_values() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.insert(9);
+    assert_eq!(tree.depth_of(&8), Some(1));
+    assert_eq!(tree.depth_of(&7), Some(3));
+    assert_eq!(tree.depth_of(&9), Some(4));
+    assert_eq!(tree.depth_of(&11), None);
+
+    assert_eq!(tree.path_to(&8), Some(vec![]));
+    assert_eq!(
+        tree.path_to(&9),
+        Some(vec![Direction::Right, Direction::Left, Direction::Left])
+    );
+    assert_eq!(tree.path_to(&2), None);
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.depth_of(&1), None);
+    assert_eq!(empty.path_to(&1), None);
+}
+
+#[test]
+fn lowest_common_ancestor_test() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.insert(9);
+    assert_eq!(tree.lowest_common_ancestor(&1, &7), Some(&6));
+    assert_eq!(tree.lowest_common_ancestor(&7, &9), Some(&8));
+    assert_eq!(tree.lowest_common_ancestor(&9, &20), Some(&13));
+    // a node is its own ancestor
+    assert_eq!(tree.lowest_common_ancestor(&10, &9), Some(&10));
+    assert_eq!(tree.lowest_common_ancestor(&6, &6), Some(&6));
+    // both values must be present
+    assert_eq!(tree.lowest_common_ancestor(&9, &11), None);
+    assert_eq!(tree.lowest_common_ancestor(&0, &1), None);
+}
+
+#[test]
+fn distance_test() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.insert(9);
+    assert_eq!(tree.distance(&1, &7), Some(2));
+    assert_eq!(tree.distance(&7, &9), Some(5));
+    assert_eq!(tree.distance(&10, &9), Some(1));
+    assert_eq!(tree.distance(&8, &8), Some(0));
+    assert_eq!(tree.distance(&8, &12), None);
+}
@@ -0,0 +1,512 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Compact, versioned binary file format for saving and loading trees
+//
+//
+
+use std::error;
+use std::fmt;
+use std::fmt::Debug;
+use std::io;
+use std::io::{Read, Write};
+
+use super::BST::*;
+use super::{BSTNode, Summary, BST};
+
+///
+/// File layout, all integers little endian:
+///
+///   magic     4 bytes  "GTRE"
+///   version   u16
+///   count     u64      number of nodes
+///   shape     ceil(2 * count / 8) bytes, two bits per node in pre-order:
+///                      has-left then has-right, most significant bit first
+///   values    count values in pre-order, each written by its Codec
+///   checksum  u32      FNV-1a over every byte before it, header included
+///
+/// Version 1 files only checksummed the shape and values, and are not read.
+///
+pub const MAGIC: [u8; 4] = *b"GTRE";
+pub const FORMAT_VERSION: u16 = 2;
+
+///
+/// Errors reading or writing the binary formats: tree files and the proof
+/// format built on the same encoding. Each format reports its own magic and
+/// version as the expected ones.
+///
+#[derive(Debug)]
+pub enum FormatError {
+    /// The underlying reader or writer failed
+    Io(io::Error),
+    /// The input ended before the data was complete
+    Truncated,
+    /// The input does not start with the format magic
+    BadMagic { found: [u8; 4], expected: [u8; 4] },
+    /// The input was written by an unsupported format version
+    UnsupportedVersion { found: u16, expected: u16 },
+    /// The stored checksum does not match the data
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The data is well framed but describes no valid tree or value
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Io(ref e) => write!(f, "i/o error: {}", e),
+            FormatError::Truncated => write!(f, "input ends before the data is complete"),
+            FormatError::BadMagic { found, expected } => write!(
+                f,
+                "magic is {:?}, expected {:?}",
+                String::from_utf8_lossy(&found),
+                String::from_utf8_lossy(&expected)
+            ),
+            FormatError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "format version {} is not supported, expected {}",
+                found, expected
+            ),
+            FormatError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "checksum mismatch, stored {:08x} but data gives {:08x}",
+                expected, found
+            ),
+            FormatError::Corrupt(what) => write!(f, "corrupted input: {}", what),
+        }
+    }
+}
+
+impl error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            FormatError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            FormatError::Truncated
+        } else {
+            FormatError::Io(e)
+        }
+    }
+}
+
+///
+/// Fixed encoding of a value for the binary format.
+///
+pub trait Codec: Sized {
+    fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError>;
+    fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError>;
+}
+
+macro_rules! int_codec {
+    ($($t:ty),*) => {$(
+        impl Codec for $t {
+            fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+                w.write_all(&self.to_le_bytes())?;
+                Ok(())
+            }
+
+            fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+                let mut buf = [0u8; std::mem::size_of::<$t>()];
+                r.read_exact(&mut buf)?;
+                Ok(<$t>::from_le_bytes(buf))
+            }
+        }
+    )*};
+}
+
+int_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+///
+/// Written as a u64 so files move between 32 and 64 bit platforms.
+///
+impl Codec for usize {
+    fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        (*self as u64).encode(w)
+    }
+
+    fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        let v = u64::decode(r)?;
+        if v > usize::MAX as u64 {
+            return Err(FormatError::Corrupt("usize value out of range"));
+        }
+        Ok(v as usize)
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        (*self as u8).encode(w)
+    }
+
+    fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(FormatError::Corrupt("invalid bool")),
+        }
+    }
+}
+
+impl Codec for char {
+    fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        (*self as u32).encode(w)
+    }
+
+    fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        std::char::from_u32(u32::decode(r)?).ok_or(FormatError::Corrupt("invalid char"))
+    }
+}
+
+///
+/// Fixed size byte arrays, such as wide digests, are written as they are.
+///
+impl<const N: usize> Codec for [u8; N] {
+    fn encode<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        w.write_all(self)?;
+        Ok(())
+    }
+
+    fn decode<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        let mut buf = [0u8; N];
+        r.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++Checksum+++++++++++++++++++++++++++++++++++++
+
+const FNV_OFFSET: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+fn fnv1a(mut hash: u32, bytes: &[u8]) -> u32 {
+    for b in bytes {
+        hash ^= u32::from(*b);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// Hashes everything passing through to the wrapped reader or writer. The proof
+// and change formats share it for their trailers.
+pub struct Checksummed<T> {
+    inner: T,
+    hash: u32,
+}
+
+impl<T> Checksummed<T> {
+    pub fn new(inner: T) -> Self {
+        Checksummed {
+            inner,
+            hash: FNV_OFFSET,
+        }
+    }
+
+    // FNV-1a of the bytes so far
+    pub fn hash(&self) -> u32 {
+        self.hash
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hash = fnv1a(self.hash, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hash = fnv1a(self.hash, &buf[..n]);
+        Ok(n)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+
+// A node still waiting for the subtrees its shape bits announced
+struct Frame<V, S> {
+    node: Box<BSTNode<V, S>>,
+    needs_left: bool,
+    needs_right: bool,
+}
+
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Codec,
+    S: Summary<V>,
+{
+    ///
+    /// Writes the tree in the binary format, keeping its exact shape.
+    ///
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        let nodes = self.pre_order_nodes();
+        let mut shape = vec![0u8; (nodes.len() * 2).div_ceil(8)];
+        for (i, n) in nodes.iter().enumerate() {
+            let bit = i * 2;
+            if let NonEmpty(_) = n.left {
+                shape[bit / 8] |= 0x80 >> (bit % 8);
+            }
+            if let NonEmpty(_) = n.right {
+                shape[(bit + 1) / 8] |= 0x80 >> ((bit + 1) % 8);
+            }
+        }
+
+        let mut body = Checksummed::new(&mut *w);
+        body.write_all(&MAGIC)?;
+        FORMAT_VERSION.encode(&mut body)?;
+        (nodes.len() as u64).encode(&mut body)?;
+        body.write_all(&shape)?;
+        for n in &nodes {
+            match n.val {
+                Some(ref v) => v.encode(&mut body)?,
+                None => return Err(FormatError::Corrupt("node without a value")),
+            }
+        }
+        let hash = body.hash();
+        hash.encode(w)?;
+        Ok(())
+    }
+
+    ///
+    /// Reads a tree written by `write_to`, rebuilding the same shape in O(n).
+    ///
+    /// Summaries are recomputed while the nodes are linked, so the file does not
+    /// depend on the summary type.
+    ///
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Self, FormatError> {
+        let mut body = Checksummed::new(&mut *r);
+        let mut magic = [0u8; 4];
+        body.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(FormatError::BadMagic {
+                found: magic,
+                expected: MAGIC,
+            });
+        }
+        let version = u16::decode(&mut body)?;
+        if version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion {
+                found: version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        let count = u64::decode(&mut body)?;
+
+        // Read through `take` so a corrupt count cannot make us allocate more than is there
+        let shape_len = count
+            .checked_mul(2)
+            .map(|bits| bits.div_ceil(8))
+            .ok_or(FormatError::Corrupt("node count too large"))?;
+        let mut shape = Vec::new();
+        (&mut body).take(shape_len).read_to_end(&mut shape)?;
+        if (shape.len() as u64) < shape_len {
+            return Err(FormatError::Truncated);
+        }
+        let bit = |i: u64| shape[(i / 8) as usize] & (0x80 >> (i % 8)) != 0;
+        if count % 4 != 0 && shape[shape.len() - 1] & (0xff >> ((count % 4) * 2)) != 0 {
+            return Err(FormatError::Corrupt("shape padding is not zero"));
+        }
+
+        let mut stack: Vec<Frame<V, S>> = Vec::new();
+        let mut root: Option<BST<V, S>> = None;
+        for i in 0..count {
+            if root.is_some() {
+                return Err(FormatError::Corrupt("shape describes fewer nodes than the count"));
+            }
+            stack.push(Frame {
+                node: Box::new(BSTNode {
+                    val: Some(V::decode(&mut body)?),
+                    left: Empty,
+                    right: Empty,
+                    depth: 1,
+                    summary: S::identity(),
+                }),
+                needs_left: bit(i * 2),
+                needs_right: bit(i * 2 + 1),
+            });
+
+            // Hang every finished subtree on its parent
+            while stack
+                .last()
+                .is_some_and(|f| !f.needs_left && !f.needs_right)
+            {
+                let mut done = match stack.pop() {
+                    Some(frame) => frame.node,
+                    None => break,
+                };
+                done.update_summary();
+                match stack.last_mut() {
+                    None => root = Some(NonEmpty(done)),
+                    Some(parent) => {
+                        if parent.needs_left {
+                            parent.node.left = NonEmpty(done);
+                            parent.needs_left = false;
+                        } else {
+                            parent.node.right = NonEmpty(done);
+                            parent.needs_right = false;
+                        }
+                    }
+                }
+            }
+        }
+        if !stack.is_empty() {
+            return Err(FormatError::Corrupt("shape describes more nodes than the count"));
+        }
+
+        let found = body.hash();
+        let expected = u32::decode(r)?;
+        if expected != found {
+            return Err(FormatError::ChecksumMismatch { expected, found });
+        }
+        Ok(root.unwrap_or(Empty))
+    }
+
+    fn pre_order_nodes(&self) -> Vec<&BSTNode<V, S>> {
+        let mut out = Vec::new();
+        let mut stack = vec![self];
+        while let Some(tree) = stack.pop() {
+            if let NonEmpty(ref n) = *tree {
+                out.push(&**n);
+                stack.push(&n.right);
+                stack.push(&n.left);
+            }
+        }
+        out
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Sum};
+
+#[cfg(test)]
+fn sample_bytes() -> Vec<u8> {
+    let tree: BST<i32> = sample_tree();
+    let mut out = Vec::new();
+    tree.write_to(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn binary_round_trip_keeps_shape() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.swap_right();
+
+    let mut out = Vec::new();
+    tree.write_to(&mut out).unwrap();
+    // header + 2 shape bytes + 7 values + checksum
+    assert_eq!(out.len(), 14 + 2 + 7 * 4 + 4);
+    assert_eq!(&out[..4], b"GTRE");
+
+    let loaded: BST<i32> = BST::read_from(&mut &out[..]).unwrap();
+    assert_eq!(
+        loaded.iter_pre_order().cloned().collect::<Vec<i32>>(),
+        vec![13, 6, 1, 7, 8, 10, 20]
+    );
+    assert_eq!(
+        loaded.iter_in_order().cloned().collect::<Vec<i32>>(),
+        tree.iter_in_order().cloned().collect::<Vec<i32>>()
+    );
+}
+
+#[test]
+fn binary_empty_and_deep_trees() {
+    let empty: BST<u8> = BST::new();
+    let mut out = Vec::new();
+    empty.write_to(&mut out).unwrap();
+    let loaded: BST<u8> = BST::read_from(&mut &out[..]).unwrap();
+    assert_eq!(loaded.size(), 0);
+
+    let mut chain: BST<u64, Sum<u64>> = BST::new();
+    for v in 0..2000 {
+        chain.insert(v);
+    }
+    let mut out = Vec::new();
+    chain.write_to(&mut out).unwrap();
+    let loaded: BST<u64, Sum<u64>> = BST::read_from(&mut &out[..]).unwrap();
+    assert_eq!(loaded.height(), 2000);
+    assert_eq!(loaded.summary(), Sum(1_999_000));
+}
+
+#[test]
+fn binary_other_codecs() {
+    let tree: BST<char> = "binary".chars().collect();
+    let mut out = Vec::new();
+    tree.write_to(&mut out).unwrap();
+    let loaded: BST<char> = BST::read_from(&mut &out[..]).unwrap();
+    assert_eq!(loaded.iter_in_order().collect::<String>(), "abinry");
+}
+
+#[test]
+fn binary_rejects_truncated_input() {
+    let bytes = sample_bytes();
+    for len in 0..bytes.len() {
+        match BST::<i32>::read_from(&mut &bytes[..len]) {
+            Err(FormatError::Truncated) => (),
+            other => panic!("length {}: {:?}", len, other.map(|t| t.size())),
+        }
+    }
+}
+
+#[test]
+fn binary_rejects_bad_header() {
+    let mut bytes = sample_bytes();
+    bytes[0] = b'X';
+    match BST::<i32>::read_from(&mut &bytes[..]) {
+        Err(e @ FormatError::BadMagic { .. }) => {
+            assert_eq!(e.to_string(), "magic is \"XTRE\", expected \"GTRE\"")
+        }
+        other => panic!("expected BadMagic, got {:?}", other.map(|t| t.size())),
+    }
+
+    let mut bytes = sample_bytes();
+    bytes[4] = 1;
+    match BST::<i32>::read_from(&mut &bytes[..]) {
+        Err(e @ FormatError::UnsupportedVersion { .. }) => assert_eq!(
+            e.to_string(),
+            "format version 1 is not supported, expected 2"
+        ),
+        other => panic!("expected UnsupportedVersion, got {:?}", other.map(|t| t.size())),
+    }
+}
+
+#[test]
+fn binary_detects_corruption() {
+    // a flipped value bit is caught by the checksum
+    let mut bytes = sample_bytes();
+    bytes[20] ^= 0x10;
+    assert!(matches!(
+        BST::<i32>::read_from(&mut &bytes[..]),
+        Err(FormatError::ChecksumMismatch { .. })
+    ));
+
+    // dropping the root's children leaves values the shape has no room for
+    let mut bytes = sample_bytes();
+    bytes[14] &= 0x3f;
+    assert!(matches!(
+        BST::<i32>::read_from(&mut &bytes[..]),
+        Err(FormatError::Corrupt(_))
+    ));
+
+    // the checksum covers the header and count as well
+    let bytes = sample_bytes();
+    let (data, stored) = bytes.split_at(bytes.len() - 4);
+    assert_eq!(&fnv1a(FNV_OFFSET, data).to_le_bytes(), stored);
+
+    // a huge count must not be trusted for allocation
+    let mut bytes = sample_bytes();
+    bytes[6..14].copy_from_slice(&(u64::MAX / 4).to_le_bytes());
+    assert!(BST::<i32>::read_from(&mut &bytes[..]).is_err());
+}
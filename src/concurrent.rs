@@ -0,0 +1,283 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Thread-safe binary search tree for many readers and writers
+//
+//
+
+use std::fmt::Debug;
+use std::ops::RangeBounds;
+use std::sync::{Mutex, MutexGuard, RwLock};
+
+use super::PersistentBST;
+
+///
+/// Concurrent Binary Search Tree
+///
+/// Uses copy-on-write root swapping over PersistentBST versions. Readers grab the
+/// current version under a short read lock and then walk it without holding any
+/// lock, so `contains` and range scans never wait for writers. Writers are
+/// serialized, build the next version by path copying, and publish it in a single
+/// swap. A range scan therefore always sees one consistent version.
+///
+/// Needs the `sync` feature, which gives PersistentBST the `Arc` nodes that let a
+/// version cross threads.
+///
+/// It does not implement `SearchTree`, whose lookups lend out references that
+/// could not outlive the lock. Take a `snapshot`, which does, instead.
+///
+pub struct ConcurrentBST<V> {
+    current: RwLock<PersistentBST<V>>,
+    writer: Mutex<()>,
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-ConcurrentBST+++++++++++++++++++++++++++++
+impl<V> ConcurrentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    pub fn new() -> Self {
+        ConcurrentBST {
+            current: RwLock::new(PersistentBST::new()),
+            writer: Mutex::new(()),
+        }
+    }
+
+    ///
+    /// Returns the current version. It stays as it is, whatever writers do later.
+    ///
+    /// A writer that panicked never leaves a half-built version behind, so a
+    /// poisoned lock still guards a consistent tree.
+    ///
+    pub fn snapshot(&self) -> PersistentBST<V> {
+        match self.current.read() {
+            Ok(v) => v.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        match self.writer.lock() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Runs `update` on the current version and publishes the result if it changed
+    // the size, returning whether it did
+    fn write<F>(&self, update: F) -> bool
+    where
+        F: FnOnce(&PersistentBST<V>) -> PersistentBST<V>,
+    {
+        let _guard = self.lock_writer();
+        let current = self.snapshot();
+        let next = update(&current);
+        if next.size() == current.size() {
+            return false;
+        }
+        match self.current.write() {
+            Ok(mut v) => *v = next,
+            Err(poisoned) => *poisoned.into_inner() = next,
+        }
+        true
+    }
+
+    ///
+    /// Inserts `new_val`. Returns false if the value was already present.
+    ///
+    pub fn insert(&self, new_val: V) -> bool {
+        self.write(|tree| tree.insert(new_val))
+    }
+
+    ///
+    /// Removes `val`. Returns false if the value was not present.
+    ///
+    pub fn remove(&self, val: V) -> bool {
+        self.write(|tree| tree.remove(val))
+    }
+
+    ///
+    /// returns an option of generic type V. None or Some(V).
+    ///
+    pub fn find(&self, val: V) -> Option<V> {
+        self.snapshot().find(val)
+    }
+
+    ///
+    /// If tree contains generic type V. Returns true. Otherwise returns false.
+    ///
+    pub fn contains(&self, val: V) -> bool {
+        self.find(val).is_some()
+    }
+
+    ///
+    /// Returns the values inside `range` in order, taken from a single version of the tree.
+    ///
+    pub fn range<R: RangeBounds<V>>(&self, range: R) -> Vec<V> {
+        self.snapshot().range(range).cloned().collect()
+    }
+
+    ///
+    /// returns size of tree
+    ///
+    pub fn size(&self) -> usize {
+        self.snapshot().size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+}
+
+impl<V> Default for ConcurrentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    fn default() -> Self {
+        ConcurrentBST::new()
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::thread;
+
+#[cfg(test)]
+use super::SAMPLE;
+
+#[test]
+fn concurrent_single_thread_basics() {
+    let tree: ConcurrentBST<i32> = ConcurrentBST::new();
+    for v in &SAMPLE {
+        assert!(tree.insert(*v));
+    }
+    assert!(!tree.insert(13));
+    assert_eq!(tree.size(), 7);
+    assert!(tree.contains(10));
+    assert_eq!(tree.find(20), Some(20));
+    assert_eq!(tree.range(..), vec![1, 6, 7, 8, 10, 13, 20]);
+    assert_eq!(tree.range(6..13), vec![6, 7, 8, 10]);
+    assert_eq!(tree.range(7..=13), vec![7, 8, 10, 13]);
+    assert_eq!(tree.range(14..), vec![20]);
+    assert_eq!(tree.range(21..), Vec::<i32>::new());
+
+    assert!(tree.remove(8));
+    assert!(!tree.remove(8));
+    assert!(tree.remove(1));
+    assert_eq!(tree.range(..), vec![6, 7, 10, 13, 20]);
+    assert_eq!(tree.size(), 5);
+}
+
+#[test]
+fn concurrent_stress_disjoint_inserts() {
+    let tree: Arc<ConcurrentBST<u32>> = Arc::new(ConcurrentBST::new());
+    let handles: Vec<_> = (0..8u32)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                for i in 0..500u32 {
+                    // interleave the keys so every thread writes all over the tree
+                    assert!(tree.insert(i * 8 + t));
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(tree.size(), 4000);
+    assert_eq!(tree.range(..), (0..4000).collect::<Vec<u32>>());
+}
+
+#[test]
+fn concurrent_stress_racing_writers() {
+    let tree: Arc<ConcurrentBST<u32>> = Arc::new(ConcurrentBST::new());
+    // Every thread tries to insert the same keys, only one insert may win per key
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let tree = tree.clone();
+            thread::spawn(move || (0..1000u32).filter(|v| tree.insert(*v)).count())
+        })
+        .collect();
+    let wins: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(wins, 1000);
+    assert_eq!(tree.size(), 1000);
+
+    // Same for removes
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let tree = tree.clone();
+            thread::spawn(move || (0..1000u32).filter(|v| tree.remove(*v)).count())
+        })
+        .collect();
+    let wins: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(wins, 1000);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn concurrent_stress_readers_and_writers() {
+    let tree: Arc<ConcurrentBST<u32>> = Arc::new(ConcurrentBST::new());
+    // Even keys are permanent, odd keys come and go
+    for v in (0..2000u32).filter(|v| v % 2 == 0) {
+        tree.insert(v);
+    }
+
+    let writers: Vec<_> = (0..4u32)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                for round in 0..20u32 {
+                    for v in (0..2000u32).filter(|v| v % 8 == t * 2 + 1) {
+                        if round % 2 == 0 {
+                            tree.insert(v);
+                        } else {
+                            tree.remove(v);
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    for v in (0..2000u32).filter(|v| v % 2 == 0).step_by(37) {
+                        assert!(tree.contains(v));
+                    }
+                    let scan = tree.range(500..1500);
+                    // a scan sees one version: sorted, unique and all permanent keys present
+                    assert!(scan.windows(2).all(|w| w[0] < w[1]));
+                    assert!(scan.iter().all(|v| *v >= 500 && *v < 1500));
+                    assert_eq!(scan.iter().filter(|v| *v % 2 == 0).count(), 500);
+                }
+            })
+        })
+        .collect();
+
+    for h in writers.into_iter().chain(readers) {
+        h.join().unwrap();
+    }
+    // every writer ends on a remove round
+    assert_eq!(tree.size(), 1000);
+    assert!(tree.range(..).iter().all(|v| v % 2 == 0));
+}
+
+#[test]
+fn concurrent_snapshot_is_stable() {
+    let tree: ConcurrentBST<i32> = ConcurrentBST::new();
+    for v in 0..10 {
+        tree.insert(v);
+    }
+    let before = tree.snapshot();
+    tree.remove(3);
+    tree.insert(42);
+    assert_eq!(before.size(), 10);
+    assert!(before.contains(3));
+    assert!(!before.contains(42));
+    assert_eq!(tree.range(2..5), vec![2, 4]);
+}
@@ -0,0 +1,831 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Cursors for walking and editing a BST in place
+//
+//
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::mem;
+use std::ptr;
+
+use super::BST::*;
+use super::extreme;
+use super::{BSTNode, Direction, Summary, BST};
+
+fn child<V, S>(node: &BSTNode<V, S>, side: Direction) -> &BST<V, S> {
+    match side {
+        Direction::Left => &node.left,
+        Direction::Right => &node.right,
+    }
+}
+
+fn child_mut<V, S>(node: &mut BSTNode<V, S>, side: Direction) -> &mut BST<V, S> {
+    match side {
+        Direction::Left => &mut node.left,
+        Direction::Right => &mut node.right,
+    }
+}
+
+fn is_empty<V, S>(tree: &BST<V, S>) -> bool {
+    match *tree {
+        Empty => true,
+        NonEmpty(_) => false,
+    }
+}
+
+// True if `tree` is rooted at exactly `node`
+fn holds<V, S>(tree: &BST<V, S>, node: &BSTNode<V, S>) -> bool {
+    match *tree {
+        Empty => false,
+        NonEmpty(ref n) => ptr::eq(&**n, node),
+    }
+}
+
+//++++++++++++++++++++++++++++++++++Cursor+++++++++++++++++++++++++++++++++++++
+
+///
+/// A read-only position in a BST.
+///
+/// The cursor keeps the path from the root to the current node, so it can step to
+/// the in-order neighbours or move up and down the structure. Stepping past either
+/// end leaves the cursor on a "ghost" position with no current value, from where
+/// `move_next` wraps to the first value and `move_prev` to the last.
+///
+pub struct Cursor<'a, V: 'a, S: 'a = ()> {
+    root: &'a BST<V, S>,
+    stack: Vec<&'a BSTNode<V, S>>,
+}
+
+impl<'a, V: 'a, S: 'a> Clone for Cursor<'a, V, S> {
+    fn clone(&self) -> Self {
+        Cursor {
+            root: self.root,
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+impl<'a, V: 'a, S: 'a> Cursor<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn push_extreme(&mut self, mut tree: &'a BST<V, S>, side: Direction) {
+        while let NonEmpty(ref node) = *tree {
+            self.stack.push(node);
+            tree = child(node, side);
+        }
+    }
+
+    // Pops nodes until the one popped was the `side` child of the new top
+    fn pop_until_child_of(&mut self, side: Direction) {
+        while let Some(node) = self.stack.pop() {
+            if let Some(parent) = self.stack.last() {
+                if holds(child(parent, side), node) {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Nearest ancestor whose `side` subtree holds the current node
+    fn ancestor_from(&self, side: Direction) -> Option<&'a V> {
+        for i in (1..self.stack.len()).rev() {
+            let parent: &'a BSTNode<V, S> = self.stack[i - 1];
+            if holds(child(parent, side), self.stack[i]) {
+                return parent.val.as_ref();
+            }
+        }
+        None
+    }
+
+    ///
+    /// Returns the value under the cursor, or None on the ghost position.
+    ///
+    pub fn current(&self) -> Option<&'a V> {
+        self.stack.last().cloned().and_then(|n| n.val.as_ref())
+    }
+
+    ///
+    /// Moves to the next value in order.
+    ///
+    pub fn move_next(&mut self) {
+        match self.stack.last().cloned() {
+            None => {
+                let root = self.root;
+                self.push_extreme(root, Direction::Left);
+            }
+            Some(node) => match node.right {
+                NonEmpty(_) => {
+                    self.push_extreme(&node.right, Direction::Left);
+                }
+                Empty => self.pop_until_child_of(Direction::Left),
+            },
+        }
+    }
+
+    ///
+    /// Moves to the previous value in order.
+    ///
+    pub fn move_prev(&mut self) {
+        match self.stack.last().cloned() {
+            None => {
+                let root = self.root;
+                self.push_extreme(root, Direction::Right);
+            }
+            Some(node) => match node.left {
+                NonEmpty(_) => {
+                    self.push_extreme(&node.left, Direction::Right);
+                }
+                Empty => self.pop_until_child_of(Direction::Right),
+            },
+        }
+    }
+
+    ///
+    /// Returns the next value in order without moving.
+    ///
+    pub fn peek_next(&self) -> Option<&'a V> {
+        match self.stack.last().cloned() {
+            None => extreme(self.root, Direction::Left),
+            Some(node) => match node.right {
+                NonEmpty(_) => extreme(&node.right, Direction::Left),
+                Empty => self.ancestor_from(Direction::Left),
+            },
+        }
+    }
+
+    ///
+    /// Returns the previous value in order without moving.
+    ///
+    pub fn peek_prev(&self) -> Option<&'a V> {
+        match self.stack.last().cloned() {
+            None => extreme(self.root, Direction::Right),
+            Some(node) => match node.left {
+                NonEmpty(_) => extreme(&node.left, Direction::Right),
+                Empty => self.ancestor_from(Direction::Right),
+            },
+        }
+    }
+
+    ///
+    /// Moves to the parent node. Returns false at the root or on the ghost position.
+    ///
+    pub fn move_parent(&mut self) -> bool {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    ///
+    /// Moves to the left child. Returns false if there is none.
+    ///
+    pub fn move_left(&mut self) -> bool {
+        self.move_child(Direction::Left)
+    }
+
+    ///
+    /// Moves to the right child. Returns false if there is none.
+    ///
+    pub fn move_right(&mut self) -> bool {
+        self.move_child(Direction::Right)
+    }
+
+    fn move_child(&mut self, side: Direction) -> bool {
+        let node = match self.stack.last().cloned() {
+            None => return false,
+            Some(n) => n,
+        };
+        match *child(node, side) {
+            Empty => false,
+            NonEmpty(ref c) => {
+                self.stack.push(c);
+                true
+            }
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++CursorMut+++++++++++++++++++++++++++++++++++++
+
+// A node whose `side` child has been lifted out into the cursor's focus
+struct Crumb<V, S> {
+    node: Box<BSTNode<V, S>>,
+    side: Direction,
+}
+
+///
+/// A position in a BST that can edit the tree around it.
+///
+/// While the cursor is alive it owns the tree: the current subtree is held as the
+/// focus and the nodes above it are kept on a path with their link to the focus
+/// taken out. Every step is O(1) and edits never start again from the root. The
+/// tree is put back together when the cursor is dropped.
+///
+pub struct CursorMut<'a, V: 'a, S: 'a = ()>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    tree: &'a mut BST<V, S>,
+    focus: BST<V, S>,
+    path: Vec<Crumb<V, S>>,
+    ghost: bool,
+}
+
+impl<'a, V: 'a, S: 'a> CursorMut<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn new(tree: &'a mut BST<V, S>) -> Self {
+        let focus = mem::replace(tree, Empty);
+        CursorMut {
+            tree,
+            focus,
+            path: Vec::new(),
+            ghost: false,
+        }
+    }
+
+    // Steps into the `side` child of the focus
+    fn descend(&mut self, side: Direction) {
+        if let NonEmpty(mut node) = mem::replace(&mut self.focus, Empty) {
+            self.focus = mem::replace(child_mut(&mut node, side), Empty);
+            self.path.push(Crumb { node, side });
+        }
+    }
+
+    // Steps back up to the parent, returning which child the focus was
+    fn ascend(&mut self) -> Option<Direction> {
+        let Crumb { mut node, side } = self.path.pop()?;
+        *child_mut(&mut node, side) = mem::replace(&mut self.focus, Empty);
+        node.update_summary();
+        self.focus = NonEmpty(node);
+        Some(side)
+    }
+
+    fn ascend_to_root(&mut self) {
+        while self.ascend().is_some() {}
+    }
+}
+
+impl<'a, V: 'a, S: 'a> Drop for CursorMut<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn drop(&mut self) {
+        self.ascend_to_root();
+        *self.tree = mem::replace(&mut self.focus, Empty);
+    }
+}
+
+impl<'a, V: 'a, S: 'a> CursorMut<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn focus_child_is_empty(&self, side: Direction) -> bool {
+        match self.focus {
+            Empty => true,
+            NonEmpty(ref node) => is_empty(child(node, side)),
+        }
+    }
+
+    fn descend_extreme(&mut self, side: Direction) {
+        while !self.focus_child_is_empty(side) {
+            self.descend(side);
+        }
+    }
+
+    // Climbs until the focus was the `side` child of its parent. Lands on the ghost
+    // position if there is no such ancestor.
+    fn ascend_until_child(&mut self, side: Direction) {
+        loop {
+            match self.ascend() {
+                Some(s) if s == side => return,
+                Some(_) => {}
+                None => {
+                    self.ghost = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn ancestor_from(&self, side: Direction) -> Option<&V> {
+        self.path
+            .iter()
+            .rev()
+            .find(|c| c.side == side)
+            .and_then(|c| c.node.val.as_ref())
+    }
+
+    ///
+    /// Returns the value under the cursor, or None on the ghost position.
+    ///
+    pub fn current(&self) -> Option<&V> {
+        if self.ghost {
+            return None;
+        }
+        match self.focus {
+            Empty => None,
+            NonEmpty(ref node) => node.val.as_ref(),
+        }
+    }
+
+    ///
+    /// Moves to the next value in order.
+    ///
+    pub fn move_next(&mut self) {
+        if self.ghost {
+            if let NonEmpty(_) = self.focus {
+                self.ghost = false;
+                self.descend_extreme(Direction::Left);
+            }
+        } else if self.focus_child_is_empty(Direction::Right) {
+            self.ascend_until_child(Direction::Left);
+        } else {
+            self.descend(Direction::Right);
+            self.descend_extreme(Direction::Left);
+        }
+    }
+
+    ///
+    /// Moves to the previous value in order.
+    ///
+    pub fn move_prev(&mut self) {
+        if self.ghost {
+            if let NonEmpty(_) = self.focus {
+                self.ghost = false;
+                self.descend_extreme(Direction::Right);
+            }
+        } else if self.focus_child_is_empty(Direction::Left) {
+            self.ascend_until_child(Direction::Right);
+        } else {
+            self.descend(Direction::Left);
+            self.descend_extreme(Direction::Right);
+        }
+    }
+
+    ///
+    /// Returns the next value in order without moving.
+    ///
+    pub fn peek_next(&self) -> Option<&V> {
+        match self.focus {
+            Empty => None,
+            NonEmpty(_) if self.ghost => extreme(&self.focus, Direction::Left),
+            NonEmpty(ref node) => match node.right {
+                NonEmpty(_) => extreme(&node.right, Direction::Left),
+                Empty => self.ancestor_from(Direction::Left),
+            },
+        }
+    }
+
+    ///
+    /// Returns the previous value in order without moving.
+    ///
+    pub fn peek_prev(&self) -> Option<&V> {
+        match self.focus {
+            Empty => None,
+            NonEmpty(_) if self.ghost => extreme(&self.focus, Direction::Right),
+            NonEmpty(ref node) => match node.left {
+                NonEmpty(_) => extreme(&node.left, Direction::Right),
+                Empty => self.ancestor_from(Direction::Right),
+            },
+        }
+    }
+
+    ///
+    /// Moves to the parent node. Returns false at the root or on the ghost position.
+    ///
+    pub fn move_parent(&mut self) -> bool {
+        !self.ghost && self.ascend().is_some()
+    }
+
+    ///
+    /// Moves to the left child. Returns false if there is none.
+    ///
+    pub fn move_left(&mut self) -> bool {
+        self.move_child(Direction::Left)
+    }
+
+    ///
+    /// Moves to the right child. Returns false if there is none.
+    ///
+    pub fn move_right(&mut self) -> bool {
+        self.move_child(Direction::Right)
+    }
+
+    fn move_child(&mut self, side: Direction) -> bool {
+        if self.ghost || self.focus_child_is_empty(side) {
+            return false;
+        }
+        self.descend(side);
+        true
+    }
+
+    ///
+    /// Inserts `val` directly after the current value and stays in place. The value is
+    /// handed back if it does not sort strictly between the current and next value.
+    /// On the ghost position the value goes in front of the first value.
+    ///
+    pub fn insert_after(&mut self, val: V) -> Result<(), V> {
+        let fits = if self.ghost {
+            extreme(&self.focus, Direction::Left).is_none_or(|first| val < *first)
+        } else {
+            self.current().is_some_and(|cur| val > *cur)
+                && self.peek_next().is_none_or(|next| val < *next)
+        };
+        if !fits {
+            return Err(val);
+        }
+        // The gap between the current and next value is always reached through the
+        // right subtree of the current node
+        match self.focus {
+            NonEmpty(ref mut node) if !self.ghost => {
+                node.right.insert(val);
+                node.update_summary();
+            }
+            _ => {
+                self.focus.insert(val);
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Inserts `val` directly before the current value and stays in place. The value is
+    /// handed back if it does not sort strictly between the previous and current value.
+    /// On the ghost position the value goes after the last value.
+    ///
+    pub fn insert_before(&mut self, val: V) -> Result<(), V> {
+        let fits = if self.ghost {
+            extreme(&self.focus, Direction::Right).is_none_or(|last| val > *last)
+        } else {
+            self.current().is_some_and(|cur| val < *cur)
+                && self.peek_prev().is_none_or(|prev| val > *prev)
+        };
+        if !fits {
+            return Err(val);
+        }
+        match self.focus {
+            NonEmpty(ref mut node) if !self.ghost => {
+                node.left.insert(val);
+                node.update_summary();
+            }
+            _ => {
+                self.focus.insert(val);
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Removes the current value and moves to the next one.
+    ///
+    pub fn remove_current(&mut self) -> Option<V> {
+        if self.ghost {
+            return None;
+        }
+        let mut node = match mem::replace(&mut self.focus, Empty) {
+            Empty => return None,
+            NonEmpty(node) => node,
+        };
+        let val = node.val;
+        match (is_empty(&node.left), is_empty(&node.right)) {
+            (false, false) => {
+                // The successor moves into this node, so the cursor stays put
+                node.val = node.right.take_min();
+                node.update_summary();
+                self.focus = NonEmpty(node);
+            }
+            (true, _) => {
+                self.focus = mem::replace(&mut node.right, Empty);
+                if is_empty(&self.focus) {
+                    self.ascend_until_child(Direction::Left);
+                } else {
+                    self.descend_extreme(Direction::Left);
+                }
+            }
+            (false, true) => {
+                self.focus = mem::replace(&mut node.left, Empty);
+                self.ascend_until_child(Direction::Left);
+            }
+        }
+        val
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Returns a cursor on `val`, or None if the tree does not contain it.
+    ///
+    pub fn cursor_at(&self, val: &V) -> Option<Cursor<'_, V, S>> {
+        let mut cursor = Cursor {
+            root: self,
+            stack: Vec::new(),
+        };
+        let mut tree = self;
+        while let NonEmpty(ref node) = *tree {
+            cursor.stack.push(node);
+            match node.val.as_ref().cmp(&Some(val)) {
+                Ordering::Equal => return Some(cursor),
+                Ordering::Greater => tree = &node.left,
+                Ordering::Less => tree = &node.right,
+            }
+        }
+        None
+    }
+
+    ///
+    /// Returns a cursor on the first value not less than `val`, or on the ghost
+    /// position if every value is less.
+    ///
+    pub fn lower_bound_cursor(&self, val: &V) -> Cursor<'_, V, S> {
+        let mut cursor = Cursor {
+            root: self,
+            stack: Vec::new(),
+        };
+        let mut best = 0;
+        let mut tree = self;
+        while let NonEmpty(ref node) = *tree {
+            cursor.stack.push(node);
+            match node.val.as_ref().cmp(&Some(val)) {
+                Ordering::Equal => return cursor,
+                Ordering::Greater => {
+                    best = cursor.stack.len();
+                    tree = &node.left;
+                }
+                Ordering::Less => tree = &node.right,
+            }
+        }
+        cursor.stack.truncate(best);
+        cursor
+    }
+
+    ///
+    /// Returns an editing cursor on `val`, or None if the tree does not contain it.
+    ///
+    pub fn cursor_mut_at(&mut self, val: &V) -> Option<CursorMut<'_, V, S>> {
+        let mut cursor = CursorMut::new(self);
+        loop {
+            let ord = match cursor.focus {
+                Empty => return None,
+                NonEmpty(ref node) => node.val.as_ref().cmp(&Some(val)),
+            };
+            match ord {
+                Ordering::Equal => return Some(cursor),
+                Ordering::Greater => cursor.descend(Direction::Left),
+                Ordering::Less => cursor.descend(Direction::Right),
+            }
+        }
+    }
+
+    ///
+    /// Returns an editing cursor on the first value not less than `val`, or on the
+    /// ghost position if every value is less.
+    ///
+    pub fn lower_bound_cursor_mut(&mut self, val: &V) -> CursorMut<'_, V, S> {
+        let mut cursor = CursorMut::new(self);
+        let mut best = None;
+        loop {
+            let ord = match cursor.focus {
+                Empty => break,
+                NonEmpty(ref node) => node.val.as_ref().cmp(&Some(val)),
+            };
+            match ord {
+                Ordering::Equal => return cursor,
+                Ordering::Greater => {
+                    best = Some(cursor.path.len());
+                    cursor.descend(Direction::Left);
+                }
+                Ordering::Less => cursor.descend(Direction::Right),
+            }
+        }
+        match best {
+            Some(depth) => {
+                while cursor.path.len() > depth {
+                    cursor.ascend();
+                }
+            }
+            None => {
+                cursor.ascend_to_root();
+                cursor.ghost = true;
+            }
+        }
+        cursor
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn cursor_walks_in_order() {
+    let tree: BST<i32> = sample_tree();
+    let mut cursor = tree.cursor_at(&7).unwrap();
+    assert_eq!(cursor.current(), Some(&7));
+
+    let mut forward = Vec::new();
+    while let Some(v) = cursor.current() {
+        forward.push(*v);
+        cursor.move_next();
+    }
+    assert_eq!(forward, vec![7, 8, 10, 13, 20]);
+
+    // from the ghost position move_prev wraps to the last value
+    let mut backward = Vec::new();
+    cursor.move_prev();
+    while let Some(v) = cursor.current() {
+        backward.push(*v);
+        cursor.move_prev();
+    }
+    assert_eq!(backward, vec![20, 13, 10, 8, 7, 6, 1]);
+
+    cursor.move_next();
+    assert_eq!(cursor.current(), Some(&1));
+}
+
+#[test]
+fn cursor_at_missing_value() {
+    let tree: BST<i32> = sample_tree();
+    assert!(tree.cursor_at(&9).is_none());
+    let empty: BST<i32> = BST::new();
+    assert!(empty.cursor_at(&9).is_none());
+}
+
+#[test]
+fn cursor_lower_bound() {
+    let tree: BST<i32> = sample_tree();
+    assert_eq!(tree.lower_bound_cursor(&9).current(), Some(&10));
+    assert_eq!(tree.lower_bound_cursor(&10).current(), Some(&10));
+    assert_eq!(tree.lower_bound_cursor(&0).current(), Some(&1));
+    assert_eq!(tree.lower_bound_cursor(&14).current(), Some(&20));
+    assert_eq!(tree.lower_bound_cursor(&21).current(), None);
+}
+
+#[test]
+fn cursor_peek() {
+    let tree: BST<i32> = sample_tree();
+    let cursor = tree.cursor_at(&7).unwrap();
+    assert_eq!(cursor.peek_prev(), Some(&6));
+    assert_eq!(cursor.peek_next(), Some(&8));
+    assert_eq!(cursor.current(), Some(&7));
+
+    let cursor = tree.cursor_at(&20).unwrap();
+    assert_eq!(cursor.peek_next(), None);
+    let cursor = tree.cursor_at(&1).unwrap();
+    assert_eq!(cursor.peek_prev(), None);
+    let ghost = tree.lower_bound_cursor(&50);
+    assert_eq!(ghost.peek_next(), Some(&1));
+    assert_eq!(ghost.peek_prev(), Some(&20));
+}
+
+#[test]
+fn cursor_moves_through_structure() {
+    let tree: BST<i32> = sample_tree();
+    let mut cursor = tree.cursor_at(&8).unwrap();
+    assert!(!cursor.move_parent());
+    assert!(cursor.move_left());
+    assert_eq!(cursor.current(), Some(&6));
+    assert!(cursor.move_right());
+    assert_eq!(cursor.current(), Some(&7));
+    assert!(!cursor.move_right());
+    assert!(cursor.move_parent());
+    assert!(cursor.move_parent());
+    assert_eq!(cursor.current(), Some(&8));
+
+    // in-order steps still work after moving by structure
+    assert!(cursor.move_right());
+    cursor.move_prev();
+    assert_eq!(cursor.current(), Some(&10));
+}
+
+#[test]
+fn cursor_mut_walks_and_restores_tree() {
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&6).unwrap();
+        assert_eq!(cursor.peek_prev(), Some(&1));
+        assert_eq!(cursor.peek_next(), Some(&7));
+        cursor.move_next();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&10));
+        assert!(cursor.move_parent());
+        assert_eq!(cursor.current(), Some(&13));
+    }
+    let mut pre_vec: Vec<i32> = vec![8, 6, 1, 7, 13, 10, 20];
+    pre_vec.reverse();
+    for node in tree.iter_pre_order() {
+        assert_eq!(node, &pre_vec.pop().unwrap());
+    }
+    assert!(tree.cursor_mut_at(&9).is_none());
+    assert_eq!(tree.size(), 7);
+}
+
+#[test]
+fn cursor_mut_insert_checks_order() {
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&8).unwrap();
+        assert_eq!(cursor.insert_after(9), Ok(()));
+        assert_eq!(cursor.insert_after(11), Err(11));
+        assert_eq!(cursor.insert_after(8), Err(8));
+        assert_eq!(cursor.insert_before(7), Err(7));
+        assert_eq!(cursor.insert_before(5), Err(5));
+        assert_eq!(cursor.current(), Some(&8));
+        assert_eq!(cursor.peek_next(), Some(&9));
+
+        cursor.move_prev();
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&1));
+        assert_eq!(cursor.insert_before(0), Ok(()));
+        assert_eq!(cursor.insert_after(3), Ok(()));
+        assert_eq!(cursor.peek_next(), Some(&3));
+    }
+    let mut in_vec: Vec<i32> = vec![0, 1, 3, 6, 7, 8, 9, 10, 13, 20];
+    in_vec.reverse();
+    for node in tree.iter_in_order() {
+        assert_eq!(node, &in_vec.pop().unwrap());
+    }
+    assert!(in_vec.is_empty());
+}
+
+#[test]
+fn cursor_mut_insert_on_ghost() {
+    let mut tree: BST<i32> = BST::new();
+    {
+        let mut cursor = tree.lower_bound_cursor_mut(&0);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.insert_after(5), Ok(()));
+        assert_eq!(cursor.insert_after(3), Ok(()));
+        assert_eq!(cursor.insert_after(4), Err(4));
+        assert_eq!(cursor.insert_before(9), Ok(()));
+        assert_eq!(cursor.insert_before(6), Err(6));
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&3));
+    }
+    assert_eq!(tree.iter_in_order().cloned().collect::<Vec<i32>>(), vec![3, 5, 9]);
+}
+
+#[test]
+fn cursor_mut_remove_current() {
+    // leaf
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&7).unwrap();
+        assert_eq!(cursor.remove_current(), Some(7));
+        assert_eq!(cursor.current(), Some(&8));
+    }
+    assert_eq!(tree.iter_in_order().cloned().collect::<Vec<i32>>(), vec![1, 6, 8, 10, 13, 20]);
+
+    // two children
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&8).unwrap();
+        assert_eq!(cursor.remove_current(), Some(8));
+        assert_eq!(cursor.current(), Some(&10));
+        assert_eq!(cursor.peek_prev(), Some(&7));
+    }
+    assert_eq!(tree.peek(), Some(&10));
+    assert_eq!(tree.iter_in_order().cloned().collect::<Vec<i32>>(), vec![1, 6, 7, 10, 13, 20]);
+
+    // last value lands on the ghost position
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&20).unwrap();
+        assert_eq!(cursor.remove_current(), Some(20));
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+    }
+    assert_eq!(tree.iter_in_order().cloned().collect::<Vec<i32>>(), vec![1, 6, 7, 8, 10, 13]);
+}
+
+#[test]
+fn cursor_mut_remove_everything() {
+    let mut tree: BST<i32> = sample_tree();
+    {
+        let mut cursor = tree.lower_bound_cursor_mut(&0);
+        let mut removed = Vec::new();
+        while let Some(v) = cursor.remove_current() {
+            removed.push(v);
+        }
+        assert_eq!(removed, vec![1, 6, 7, 8, 10, 13, 20]);
+    }
+    assert_eq!(tree.size(), 0);
+    assert_eq!(tree.peek(), None);
+}
@@ -0,0 +1,246 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Graphviz DOT export for looking at tree shapes
+//
+//
+
+use std::fmt::Debug;
+use std::io;
+use std::io::Write;
+
+use super::BST::*;
+use super::{Summary, BST};
+
+///
+/// What `to_dot_with` / `write_dot_with` should draw besides the plain tree.
+///
+/// `search_path` highlights the nodes and edges an ordinary search for that value
+/// walks through. `mark_violations` fills every node that breaks the ordering
+/// invariant relative to its ancestors, as `swap_left`/`swap_right` can leave them.
+/// `show_empty` draws a small point for every missing child so left and right
+/// children can be told apart at a glance.
+///
+#[derive(Debug, Clone)]
+pub struct DotOptions<V> {
+    pub search_path: Option<V>,
+    pub mark_violations: bool,
+    pub show_empty: bool,
+}
+
+impl<V> Default for DotOptions<V> {
+    fn default() -> Self {
+        DotOptions {
+            search_path: None,
+            mark_violations: true,
+            show_empty: true,
+        }
+    }
+}
+
+// A node to draw with what is known about it from its ancestors
+struct Pending<'a, V: 'a, S: 'a> {
+    tree: &'a BST<V, S>,
+    parent: Option<(usize, &'static str, bool)>,
+    lower: Option<V>,
+    upper: Option<V>,
+    on_path: bool,
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Returns the tree as a Graphviz digraph using the default options.
+    ///
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+
+    ///
+    /// Returns the tree as a Graphviz digraph.
+    ///
+    pub fn to_dot_with(&self, options: &DotOptions<V>) -> String {
+        let mut out = Vec::new();
+        // Writing into a Vec cannot fail and everything written is valid UTF-8
+        let _ = self.write_dot_with(&mut out, options);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    ///
+    /// Writes the tree as a Graphviz digraph, e.g. for `dot -Tsvg`, using the
+    /// default options.
+    ///
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_dot_with(w, &DotOptions::default())
+    }
+
+    ///
+    /// Writes the tree as a Graphviz digraph.
+    ///
+    pub fn write_dot_with<W: Write>(&self, w: &mut W, options: &DotOptions<V>) -> io::Result<()> {
+        writeln!(w, "digraph BST {{")?;
+        writeln!(w, "    node [shape=circle];")?;
+
+        let mut next_id = 0;
+        let mut stack = vec![Pending {
+            tree: self,
+            parent: None,
+            lower: None,
+            upper: None,
+            on_path: options.search_path.is_some(),
+        }];
+        while let Some(p) = stack.pop() {
+            let id = next_id;
+            next_id += 1;
+
+            let n = match *p.tree {
+                NonEmpty(ref n) => n,
+                Empty => {
+                    if options.show_empty {
+                        writeln!(w, "    e{} [shape=point];", id)?;
+                        if let Some((parent, side, _)) = p.parent {
+                            writeln!(w, "    n{} -> e{} [label=\"{}\"];", parent, id, side)?;
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            let mut attrs = match n.val {
+                Some(ref v) => format!("label=\"{}\"", escape(&format!("{:?}", v))),
+                None => "label=\"\", shape=doublecircle".to_string(),
+            };
+            let broken = match n.val {
+                Some(v) => {
+                    p.lower.is_some_and(|lo| v <= lo) || p.upper.is_some_and(|hi| v >= hi)
+                }
+                None => false,
+            };
+            if options.mark_violations && broken {
+                attrs.push_str(", style=filled, fillcolor=salmon");
+            }
+            if p.on_path {
+                attrs.push_str(", color=blue, penwidth=2");
+                if n.val.is_some() && n.val == options.search_path {
+                    attrs.push_str(", peripheries=2");
+                }
+            }
+            writeln!(w, "    n{} [{}];", id, attrs)?;
+            if let Some((parent, side, edge_on_path)) = p.parent {
+                if edge_on_path {
+                    writeln!(
+                        w,
+                        "    n{} -> n{} [label=\"{}\", color=blue, penwidth=2];",
+                        parent, id, side
+                    )?;
+                } else {
+                    writeln!(w, "    n{} -> n{} [label=\"{}\"];", parent, id, side)?;
+                }
+            }
+
+            // Which way a search for the highlighted value would continue from here
+            let (path_left, path_right) = match (p.on_path, options.search_path, n.val) {
+                (true, Some(target), Some(v)) => (target < v, target > v),
+                _ => (false, false),
+            };
+            // Children inherit the tightest bounds seen so far, valid or not
+            let (lower, upper) = match n.val {
+                Some(v) => (Some(v), Some(v)),
+                None => (p.lower, p.upper),
+            };
+            stack.push(Pending {
+                tree: &n.right,
+                parent: Some((id, "R", path_right)),
+                lower,
+                upper: p.upper,
+                on_path: path_right,
+            });
+            stack.push(Pending {
+                tree: &n.left,
+                parent: Some((id, "L", path_left)),
+                lower: p.lower,
+                upper,
+                on_path: path_left,
+            });
+        }
+        writeln!(w, "}}")
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn dot_plain_tree() {
+    let tree: BST<i32> = vec![8, 13, 6].into_iter().collect();
+    let dot = tree.to_dot_with(&DotOptions {
+        search_path: None,
+        mark_violations: false,
+        show_empty: false,
+    });
+    assert_eq!(
+        dot,
+        "digraph BST {\n    node [shape=circle];\n    n0 [label=\"8\"];\n    n1 [label=\"6\"];\n    n0 -> n1 [label=\"L\"];\n    n4 [label=\"13\"];\n    n0 -> n4 [label=\"R\"];\n}\n"
+    );
+}
+
+#[test]
+fn dot_empty_placeholders() {
+    let tree: BST<i32> = vec![8, 13, 6].into_iter().collect();
+    let dot = tree.to_dot();
+    assert_eq!(dot.matches("[shape=point]").count(), 4);
+    assert!(dot.contains("n1 -> e2 [label=\"L\"];"));
+    assert!(dot.contains("n4 -> e6 [label=\"R\"];"));
+    assert!(!dot.contains("salmon"));
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.to_dot(), "digraph BST {\n    node [shape=circle];\n    e0 [shape=point];\n}\n");
+
+    // write_dot draws the same with the default options
+    let mut out = Vec::new();
+    tree.write_dot(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), dot);
+}
+
+#[test]
+fn dot_search_path() {
+    let mut tree: BST<i32> = vec![8, 13, 6].into_iter().collect();
+    tree.insert(7);
+    let options = DotOptions {
+        search_path: Some(7),
+        ..DotOptions::default()
+    };
+    let dot = tree.to_dot_with(&options);
+    assert!(dot.contains("n0 [label=\"8\", color=blue, penwidth=2];"));
+    assert!(dot.contains("n1 [label=\"6\", color=blue, penwidth=2];"));
+    assert!(dot.contains("n1 -> n3 [label=\"R\", color=blue, penwidth=2];"));
+    assert!(dot.contains("n3 [label=\"7\", color=blue, penwidth=2, peripheries=2];"));
+    assert!(dot.contains("n0 -> n1 [label=\"L\", color=blue, penwidth=2];"));
+    assert!(dot.contains("n6 [label=\"13\"];"));
+}
+
+#[test]
+fn dot_marks_ordering_violations() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.swap_right();
+    let dot = tree.to_dot();
+    // 13 is now the root, 8 and its left child 10 ended up on the wrong side of it
+    assert_eq!(dot.matches("salmon").count(), 2);
+    assert!(dot.contains("[label=\"8\", style=filled, fillcolor=salmon];"));
+    assert!(dot.contains("[label=\"10\", style=filled, fillcolor=salmon];"));
+    assert!(dot.contains("[label=\"20\"];"));
+}
+
+#[test]
+fn dot_escapes_labels() {
+    let tree: BST<&str> = vec!["say \"hi\""].into_iter().collect();
+    assert!(tree.to_dot().contains(r#"label="\"say \\\"hi\\\"\"""#));
+}
@@ -0,0 +1,662 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Interval tree for overlap and stabbing queries
+//
+//
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::mem;
+
+use super::BST::*;
+use super::{BSTInOrderIter, BSTNode, Summary, BST};
+
+///
+/// A closed interval [start, end], never with start after end.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T> Interval<T>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    ///
+    /// Creates the interval [start, end]. Panics if `start > end`.
+    ///
+    pub fn new(start: T, end: T) -> Self {
+        match Interval::try_new(start, end) {
+            Some(interval) => interval,
+            None => panic!("interval start {:?} is after end {:?}", start, end),
+        }
+    }
+
+    ///
+    /// Creates the interval [start, end], or None if `start > end`.
+    ///
+    pub fn try_new(start: T, end: T) -> Option<Self> {
+        if start <= end {
+            Some(Interval { start, end })
+        } else {
+            None
+        }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    ///
+    /// Returns true if the two intervals share at least one point.
+    ///
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    ///
+    /// Returns true if `point` lies inside the interval.
+    ///
+    pub fn contains_point(&self, point: T) -> bool {
+        self.start <= point && point <= self.end
+    }
+}
+
+// What the BST orders: an interval and the slot of its value. Ordered by the
+// interval alone, so a lookup does not need to know the slot.
+#[derive(Debug, Clone, Copy)]
+struct Key<T> {
+    interval: Interval<T>,
+    slot: usize,
+}
+
+impl<T: Ord> PartialEq for Key<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.interval == other.interval
+    }
+}
+
+impl<T: Ord> Eq for Key<T> {}
+
+impl<T: Ord> PartialOrd for Key<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Key<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.interval.cmp(&other.interval)
+    }
+}
+
+// Per-node summary: the largest end point in the subtree, which lets queries skip
+// subtrees ending before the query starts, and the number of intervals, which
+// tells the tree where to rebalance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Reach<T> {
+    max_end: Option<T>,
+    count: usize,
+}
+
+impl<T: Copy + Ord> Summary<Key<T>> for Reach<T> {
+    fn identity() -> Self {
+        Reach {
+            max_end: None,
+            count: 0,
+        }
+    }
+
+    fn summarize(key: &Key<T>) -> Self {
+        Reach {
+            max_end: Some(key.interval.end),
+            count: 1,
+        }
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Reach {
+            max_end: cmp::max(self.max_end, other.max_end),
+            count: self.count + other.count,
+        }
+    }
+}
+
+type INode<T> = BSTNode<Key<T>, Reach<T>>;
+
+///
+/// Interval Tree
+///
+/// A BST of intervals ordered by start (then end), whose per-node summary records
+/// the maximum end point in the subtree, so queries can skip any subtree that ends
+/// before the query starts. Values are kept beside the tree, so they need not be
+/// `Copy` like BST values.
+///
+/// Balance is kept the scapegoat way: an insert landing deeper than
+/// log_{3/2}(len) rebuilds the lowest subtree on its path that has more than 2/3 of
+/// its intervals on one side, and the whole tree is rebuilt once removals shrink it
+/// below 2/3 of its largest size. The height stays O(log n), so `get` and
+/// `find_any_overlap` are O(log n) and insert and remove O(log n) amortized;
+/// overlap and stabbing iteration cost O(log n) plus the work for each reported
+/// interval.
+///
+pub struct IntervalTree<T, V> {
+    tree: BST<Key<T>, Reach<T>>,
+    values: Vec<Option<V>>,
+    free: Vec<usize>,
+    max_len: usize,
+}
+
+//++++++++++++++++++++++++++++++++++Iterators+++++++++++++++++++++++++++++++++++++
+
+///
+/// In order iteration over the intervals overlapping a query interval.
+///
+pub struct OverlapIter<'a, T: 'a, V: 'a> {
+    query: Interval<T>,
+    iter_stack: Vec<&'a INode<T>>,
+    values: &'a [Option<V>],
+}
+
+impl<'a, T: 'a, V: 'a> OverlapIter<'a, T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    // Pushes the left spine, skipping subtrees that end before the query starts
+    fn push_leftmost(&mut self, mut tree: &'a BST<Key<T>, Reach<T>>) {
+        while let NonEmpty(ref node) = *tree {
+            if node.summary.max_end < Some(self.query.start) {
+                return;
+            }
+            self.iter_stack.push(node);
+            tree = &node.left;
+        }
+    }
+}
+
+impl<'a, T, V> Iterator for OverlapIter<'a, T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    type Item = (&'a Interval<T>, &'a V);
+
+    fn next(&mut self) -> Option<(&'a Interval<T>, &'a V)> {
+        while let Some(node) = self.iter_stack.pop() {
+            let key = node.val.as_ref()?;
+            // Everything from here on in order starts after the query ends
+            if key.interval.start > self.query.end {
+                self.iter_stack.clear();
+                return None;
+            }
+            self.push_leftmost(&node.right);
+            if key.interval.overlaps(&self.query) {
+                return Some((&key.interval, self.values[key.slot].as_ref()?));
+            }
+        }
+        None
+    }
+}
+
+///
+/// In order iteration over all intervals of an IntervalTree.
+///
+pub struct IntervalIter<'a, T: 'a, V: 'a>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    iter: BSTInOrderIter<'a, Key<T>, Reach<T>>,
+    values: &'a [Option<V>],
+}
+
+impl<'a, T, V> Iterator for IntervalIter<'a, T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    type Item = (&'a Interval<T>, &'a V);
+
+    fn next(&mut self) -> Option<(&'a Interval<T>, &'a V)> {
+        let key = self.iter.next()?;
+        Some((&key.interval, self.values[key.slot].as_ref()?))
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-IntervalTree+++++++++++++++++++++++++++++
+impl<T, V> IntervalTree<T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    pub fn new() -> Self {
+        IntervalTree {
+            tree: Empty,
+            values: Vec::new(),
+            free: Vec::new(),
+            max_len: 0,
+        }
+    }
+
+    // The slot holding the value of exactly `interval`
+    fn slot_of(&self, interval: &Interval<T>) -> Option<usize> {
+        let mut tree = &self.tree;
+        while let NonEmpty(ref node) = *tree {
+            let key = node.val.as_ref()?;
+            match interval.cmp(&key.interval) {
+                Ordering::Equal => return Some(key.slot),
+                Ordering::Less => tree = &node.left,
+                Ordering::Greater => tree = &node.right,
+            }
+        }
+        None
+    }
+
+    ///
+    /// Inserts `value` under `interval`, returning the previous value stored for
+    /// exactly the same interval.
+    ///
+    pub fn insert(&mut self, interval: Interval<T>, value: V) -> Option<V> {
+        if let Some(slot) = self.slot_of(&interval) {
+            return self.values[slot].replace(value);
+        }
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.values[slot] = Some(value);
+                slot
+            }
+            None => {
+                self.values.push(Some(value));
+                self.values.len() - 1
+            }
+        };
+        let key = Key { interval, slot };
+        self.tree.insert(key);
+        self.max_len = cmp::max(self.max_len, self.len());
+        self.rebuild_scapegoat(&key);
+        None
+    }
+
+    // Rebuilds the lowest unbalanced subtree above `key` if it landed too deep
+    fn rebuild_scapegoat(&mut self, key: &Key<T>) {
+        // subtree sizes along the path, the new node's last
+        let mut counts = Vec::new();
+        let mut tree = &self.tree;
+        while let NonEmpty(ref node) = *tree {
+            counts.push(node.summary.count);
+            tree = match node.val {
+                Some(ref v) if key < v => &node.left,
+                Some(ref v) if key > v => &node.right,
+                _ => break,
+            };
+        }
+        let edges = counts.len().saturating_sub(1);
+        if edges as f64 <= (self.max_len as f64).log(1.5) {
+            return;
+        }
+        let level = match (0..edges)
+            .rev()
+            .find(|&i| 3 * counts[i + 1] > 2 * counts[i])
+        {
+            Some(level) => level,
+            None => return,
+        };
+        let mut tree = &mut self.tree;
+        for _ in 0..level {
+            tree = match *tree {
+                NonEmpty(ref mut node) => {
+                    if node.val.as_ref().is_some_and(|v| key < v) {
+                        &mut node.left
+                    } else {
+                        &mut node.right
+                    }
+                }
+                Empty => return,
+            };
+        }
+        // Same keys, so the summaries above stay right
+        let keys: Vec<Key<T>> = mem::replace(tree, Empty).into_iter().collect();
+        *tree = BST::from_sorted(&keys);
+    }
+
+    ///
+    /// Removes exactly `interval`, returning its value.
+    ///
+    pub fn remove(&mut self, interval: &Interval<T>) -> Option<V> {
+        let slot = self.slot_of(interval)?;
+        self.tree.remove(Key {
+            interval: *interval,
+            slot,
+        });
+        self.free.push(slot);
+        let value = self.values[slot].take();
+        if 3 * self.len() < 2 * self.max_len {
+            self.rebuild_all();
+        }
+        value
+    }
+
+    // Rebuilds the whole tree balanced, packing the values into fresh slots
+    fn rebuild_all(&mut self) {
+        let keys: Vec<Key<T>> = mem::replace(&mut self.tree, Empty).into_iter().collect();
+        let mut values = Vec::with_capacity(keys.len());
+        let mut packed = Vec::with_capacity(keys.len());
+        for (slot, key) in keys.into_iter().enumerate() {
+            values.push(self.values[key.slot].take());
+            packed.push(Key {
+                interval: key.interval,
+                slot,
+            });
+        }
+        self.tree = BST::from_sorted(&packed);
+        self.values = values;
+        self.free.clear();
+        self.max_len = packed.len();
+    }
+
+    ///
+    /// Returns the value stored for exactly `interval`.
+    ///
+    pub fn get(&self, interval: &Interval<T>) -> Option<&V> {
+        self.slot_of(interval)
+            .and_then(|slot| self.values[slot].as_ref())
+    }
+
+    ///
+    /// Iterates, in order of start point, over every interval overlapping `query`.
+    ///
+    pub fn overlapping(&self, query: Interval<T>) -> OverlapIter<'_, T, V> {
+        let mut iter = OverlapIter {
+            query,
+            iter_stack: Vec::new(),
+            values: &self.values,
+        };
+        iter.push_leftmost(&self.tree);
+        iter
+    }
+
+    ///
+    /// Iterates, in order of start point, over every interval containing `point`.
+    ///
+    pub fn stabbing(&self, point: T) -> OverlapIter<'_, T, V> {
+        self.overlapping(Interval {
+            start: point,
+            end: point,
+        })
+    }
+
+    ///
+    /// Returns some interval overlapping `query` in O(log n), or None if there is none.
+    ///
+    pub fn find_any_overlap(&self, query: &Interval<T>) -> Option<(&Interval<T>, &V)> {
+        let mut tree = &self.tree;
+        while let NonEmpty(ref node) = *tree {
+            let key = node.val.as_ref()?;
+            if key.interval.overlaps(query) {
+                return Some((&key.interval, self.values[key.slot].as_ref()?));
+            }
+            // If the left subtree reaches the query start, any overlap that exists at
+            // all can be found there: otherwise every start in the right subtree is
+            // after the query end too.
+            tree = if node.left.summary().max_end >= Some(query.start) {
+                &node.left
+            } else {
+                &node.right
+            };
+        }
+        None
+    }
+
+    ///
+    /// Iterates over all intervals in order of start point.
+    ///
+    pub fn iter(&self) -> IntervalIter<'_, T, V> {
+        IntervalIter {
+            iter: self.tree.iter_in_order(),
+            values: &self.values,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.summary().count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// Returns the height of the tree.
+    ///
+    pub fn height(&self) -> usize {
+        self.tree.height() as usize
+    }
+}
+
+impl<T, V> Default for IntervalTree<T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq,
+{
+    fn default() -> Self {
+        IntervalTree::new()
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+// Checks ordering and the summaries, returning (height, count, max_end)
+#[cfg(test)]
+fn check_node<T: Debug + Copy + Ord>(tree: &BST<Key<T>, Reach<T>>) -> (usize, usize, Option<T>) {
+    match *tree {
+        Empty => (0, 0, None),
+        NonEmpty(ref n) => {
+            let (hl, cl, ml) = check_node(&n.left);
+            let (hr, cr, mr) = check_node(&n.right);
+            let key = n.val.unwrap();
+            if let Some(l) = n.left.peek() {
+                assert!(l.interval < key.interval);
+            }
+            if let Some(r) = n.right.peek() {
+                assert!(r.interval > key.interval);
+            }
+            let max_end = cmp::max(Some(key.interval.end), cmp::max(ml, mr));
+            assert_eq!(n.summary.max_end, max_end);
+            assert_eq!(n.summary.count, cl + cr + 1);
+            (1 + cmp::max(hl, hr), cl + cr + 1, max_end)
+        }
+    }
+}
+
+// Checks the tree and that its height is within the scapegoat bound
+#[cfg(test)]
+fn check_tree<T: Debug + Copy + Ord, V>(tree: &IntervalTree<T, V>) {
+    let (height, count, _) = check_node(&tree.tree);
+    assert_eq!(count, tree.len());
+    assert_eq!(tree.values.iter().filter(|v| v.is_some()).count(), count);
+    assert!(height as f64 <= (tree.max_len as f64).log(1.5) + 1.0);
+}
+
+#[cfg(test)]
+fn random_intervals(count: usize) -> Vec<Interval<u32>> {
+    // small linear congruential generator so the test needs no dependencies
+    let mut seed: u32 = 12345;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % 1000
+    };
+    (0..count)
+        .map(|_| {
+            let start = next();
+            Interval::new(start, start + next() % 50)
+        })
+        .collect()
+}
+
+#[test]
+fn interval_insert_and_get() {
+    let mut tree = IntervalTree::new();
+    assert_eq!(tree.insert(Interval::new(5, 10), "a"), None);
+    assert_eq!(tree.insert(Interval::new(1, 3), "b"), None);
+    assert_eq!(tree.insert(Interval::new(5, 10), "c"), Some("a"));
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.get(&Interval::new(5, 10)), Some(&"c"));
+    assert_eq!(tree.get(&Interval::new(5, 11)), None);
+    assert_eq!(
+        tree.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        vec![Interval::new(1, 3), Interval::new(5, 10)]
+    );
+}
+
+#[test]
+fn interval_overlap_queries() {
+    let mut tree = IntervalTree::new();
+    for &(s, e) in &[(15, 20), (10, 30), (17, 19), (5, 20), (12, 15), (30, 40)] {
+        tree.insert(Interval::new(s, e), s * 100 + e);
+    }
+    check_tree(&tree);
+
+    let hits: Vec<_> = tree
+        .overlapping(Interval::new(14, 16))
+        .map(|(i, _)| *i)
+        .collect();
+    assert_eq!(
+        hits,
+        vec![
+            Interval::new(5, 20),
+            Interval::new(10, 30),
+            Interval::new(12, 15),
+            Interval::new(15, 20),
+        ]
+    );
+
+    let stabbed: Vec<_> = tree.stabbing(30).map(|(_, v)| *v).collect();
+    assert_eq!(stabbed, vec![1030, 3040]);
+    assert_eq!(tree.stabbing(41).count(), 0);
+    assert_eq!(tree.stabbing(4).count(), 0);
+
+    assert!(tree.find_any_overlap(&Interval::new(21, 25)).is_some());
+    assert!(tree.find_any_overlap(&Interval::new(41, 50)).is_none());
+    let (found, _) = tree.find_any_overlap(&Interval::new(0, 5)).unwrap();
+    assert_eq!(*found, Interval::new(5, 20));
+}
+
+#[test]
+fn interval_queries_match_brute_force() {
+    let intervals = random_intervals(500);
+    let mut tree = IntervalTree::new();
+    for (i, interval) in intervals.iter().enumerate() {
+        tree.insert(*interval, i);
+    }
+    check_tree(&tree);
+
+    for query in random_intervals(50) {
+        let mut expected: Vec<Interval<u32>> = intervals
+            .iter()
+            .filter(|i| i.overlaps(&query))
+            .cloned()
+            .collect();
+        expected.sort();
+        expected.dedup();
+        let found: Vec<Interval<u32>> = tree.overlapping(query).map(|(i, _)| *i).collect();
+        assert_eq!(found, expected);
+        assert_eq!(
+            tree.find_any_overlap(&query).is_some(),
+            !expected.is_empty()
+        );
+    }
+}
+
+#[test]
+fn interval_remove_keeps_augmentation() {
+    let intervals = random_intervals(300);
+    let mut tree = IntervalTree::new();
+    for interval in &intervals {
+        tree.insert(*interval, ());
+    }
+    let count = tree.len();
+    let mut removed = 0;
+    for interval in intervals.iter().step_by(2) {
+        if tree.remove(interval).is_some() {
+            removed += 1;
+        }
+        check_tree(&tree);
+    }
+    assert_eq!(tree.len(), count - removed);
+    assert_eq!(tree.remove(&Interval::new(2000, 2001)), None);
+    for interval in intervals.iter().step_by(2) {
+        assert!(tree.get(interval).is_none());
+    }
+    for (interval, _) in tree.iter() {
+        assert_eq!(
+            tree.stabbing(interval.start)
+                .filter(|(i, _)| *i == interval)
+                .count(),
+            1
+        );
+    }
+}
+
+#[test]
+fn interval_tree_stays_balanced() {
+    // sorted inserts would make a chain without the rebuilds
+    let mut tree = IntervalTree::new();
+    for i in 0..100_000u32 {
+        tree.insert(Interval::new(i, i + 1), i);
+    }
+    // log_{3/2} of 100k is about 28.4
+    assert!(tree.height() <= 29);
+    check_tree(&tree);
+    assert_eq!(
+        tree.stabbing(500).map(|(_, v)| *v).collect::<Vec<_>>(),
+        vec![499, 500]
+    );
+
+    // shrinking packs everything again
+    for i in 0..90_000u32 {
+        assert_eq!(tree.remove(&Interval::new(i, i + 1)), Some(i));
+    }
+    check_tree(&tree);
+    assert_eq!(tree.len(), 10_000);
+    assert!(tree.values.len() < 15_000);
+    assert!(tree.height() <= 23);
+    assert_eq!(
+        tree.iter().next().map(|(i, v)| (i.start(), *v)),
+        Some((90_000, 90_000))
+    );
+}
+
+#[test]
+fn interval_values_need_not_be_copy() {
+    let mut tree = IntervalTree::new();
+    tree.insert(Interval::new(1, 4), String::from("a"));
+    tree.insert(Interval::new(2, 3), String::from("b"));
+    assert_eq!(
+        tree.insert(Interval::new(1, 4), String::from("c")),
+        Some(String::from("a"))
+    );
+    assert_eq!(tree.remove(&Interval::new(2, 3)), Some(String::from("b")));
+    assert_eq!(
+        tree.stabbing(2)
+            .map(|(_, v)| v.as_str())
+            .collect::<Vec<_>>(),
+        vec!["c"]
+    );
+}
+
+#[test]
+fn interval_bounds() {
+    let interval = Interval::new(2, 7);
+    assert_eq!((interval.start(), interval.end()), (2, 7));
+    assert!(interval.contains_point(7));
+    assert!(!interval.contains_point(8));
+    assert_eq!(Interval::try_new(5, 5), Some(Interval::new(5, 5)));
+    assert_eq!(Interval::try_new(5, 1), None);
+}
+
+#[test]
+#[should_panic]
+fn interval_rejects_reversed_bounds() {
+    Interval::new(5, 1);
+}
@@ -3,17 +3,75 @@
 //
 //
 
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
 use std::fmt::Debug;
 use self::BST::*;
+use std::cmp::Ordering;
 use std::mem;
-use std::cmp;
+use std::iter::FromIterator;
+use std::ops::{Bound, RangeBounds};
+
+mod ancestry;
+mod binary;
+#[cfg(feature = "sync")]
+mod concurrent;
+mod cursor;
+mod dot;
+mod interval;
+mod merkle;
+mod morris;
+mod obj_diff;
+mod obj_tree;
+mod persistent;
+mod pretty;
+mod proof;
+mod retain;
+mod rotate;
+mod search_tree;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod stats;
+mod subtree;
+mod summary;
+mod transform;
+mod validate;
+mod visitor;
+
+pub use binary::{Codec, FormatError, FORMAT_VERSION, MAGIC};
+#[cfg(feature = "sync")]
+pub use concurrent::ConcurrentBST;
+pub use cursor::{Cursor, CursorMut};
+pub use dot::DotOptions;
+pub use interval::{Interval, IntervalIter, IntervalTree, OverlapIter};
+pub use merkle::{HashBytes, HashState, SipState, SipTreeHasher, TreeHasher};
+pub use morris::MorrisIter;
+pub use obj_diff::{TreeChange, CHANGE_FORMAT_VERSION, CHANGE_MAGIC};
+pub use obj_tree::{NodeId, ObjHash, ObjTree, ObjTreeAncestors, ObjTreeHash, ObjTreeIter};
+pub use persistent::{PersistentBST, PersistentInOrderIter, PersistentRange};
+pub use pretty::{Layout, PrettyOptions};
+pub use proof::{verify, InclusionProof, PROOF_FORMAT_VERSION, PROOF_MAGIC};
+pub use retain::ExtractIf;
+pub use search_tree::{BinaryTreeNode, SearchTree};
+#[cfg(feature = "serde")]
+pub use serde_impl::{shape_preserving, ShapePreserving};
+pub use stats::TreeStats;
+pub use summary::{Count, Max, Min, Sum, Summary};
+pub use validate::InvariantViolation;
+pub use visitor::{TreeVisitor, VisitControl};
 
 ///  Generic Search Tree
 ///
 #[allow(dead_code)]
-enum BST<V> {
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Default)]
+pub enum BST<V, S = ()> {
+    #[default]
     Empty,
-    NonEmpty(Box<BSTNode<V>>),
+    NonEmpty(Box<BSTNode<V, S>>),
 }
 
 ///
@@ -23,30 +81,120 @@ enum BST<V> {
 ///
 #[allow(unused_variables)]
 #[allow(dead_code)]
-struct BSTNode<V> {
+pub struct BSTNode<V, S = ()> {
     val: Option<V>,
-    right: BST<V>,
-    left: BST<V>,
+    right: BST<V, S>,
+    left: BST<V, S>,
     depth: usize,
+    summary: S,
+}
+
+///
+/// Which child of a node to step into.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+impl<V, S> BSTNode<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    // Recomputes the summary after the value or a child changed
+    fn update_summary(&mut self) {
+        let own = match self.val {
+            Some(ref v) => S::summarize(v),
+            None => S::identity(),
+        };
+        self.summary = self.left.summary().combine(&own).combine(&self.right.summary());
+    }
+}
+
+fn above_start<V: Ord, R: RangeBounds<V>>(range: &R, val: &V) -> bool {
+    match range.start_bound() {
+        Bound::Included(s) => val >= s,
+        Bound::Excluded(s) => val > s,
+        Bound::Unbounded => true,
+    }
+}
+
+fn below_end<V: Ord, R: RangeBounds<V>>(range: &R, val: &V) -> bool {
+    match range.end_bound() {
+        Bound::Included(e) => val <= e,
+        Bound::Excluded(e) => val < e,
+        Bound::Unbounded => true,
+    }
+}
+
+// Which way to search for `val` from `node`, None once it is found or a node
+// without a value blocks the way
+fn toward<V: Ord, S>(node: &BSTNode<V, S>, val: &V) -> Option<Direction> {
+    match node.val.as_ref().map(|v| val.cmp(v)) {
+        Some(Ordering::Less) => Some(Direction::Left),
+        Some(Ordering::Greater) => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+// Nodes unhooked on the way down, with the side the walk continued on
+type Unzipped<V, S> = Vec<(Box<BSTNode<V, S>>, Direction)>;
+
+// Value found by always stepping to the `side` child
+fn extreme<V, S>(mut tree: &BST<V, S>, side: Direction) -> Option<&V> {
+    let mut found = None;
+    while let NonEmpty(ref node) = *tree {
+        found = node.val.as_ref();
+        tree = match side {
+            Direction::Left => &node.left,
+            Direction::Right => &node.right,
+        };
+    }
+    found
+}
+
+///
+/// Drops the nodes one at a time so long chains do not overflow the stack.
+///
+impl<V, S> Drop for BSTNode<V, S> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        for child in &mut [&mut self.left, &mut self.right] {
+            if let NonEmpty(n) = mem::replace(*child, Empty) {
+                stack.push(n);
+            }
+        }
+        while let Some(mut n) = stack.pop() {
+            for child in &mut [&mut n.left, &mut n.right] {
+                if let NonEmpty(c) = mem::replace(*child, Empty) {
+                    stack.push(c);
+                }
+            }
+            // `n` goes out of scope here with both children already taken
+        }
+    }
 }
 
 //++++++++++++++++++++++++++++++++++Iterators+++++++++++++++++++++++++++++++++++++
 
-struct BSTInOrderIntoIterator<V>
+pub struct BSTInOrderIntoIterator<V, S = ()>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    into_iter_stack: Vec<BST<V>>,
+    into_iter_stack: Vec<BST<V, S>>,
 }
 
 ///
 /// Iterator for Post-Order
 ///
-impl<V> BSTInOrderIntoIterator<V>
+impl<V, S> BSTInOrderIntoIterator<V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
 {
-    fn new(tree: BST<V>) -> BSTInOrderIntoIterator<V> {
+    fn new(tree: BST<V, S>) -> BSTInOrderIntoIterator<V, S> {
         let mut iter = BSTInOrderIntoIterator {
             into_iter_stack: Vec::new(),
         };
@@ -54,25 +202,21 @@ where
         iter
     }
 
-    fn push_leftmost(&mut self, mut tree: BST<V>) {
-        let some_left_tree = tree.take_left();
-        match some_left_tree {
-            None => {
-                self.into_iter_stack.push(tree);
-            }
-            Some(left_tree) => {
-                self.into_iter_stack.push(tree);
-                self.push_leftmost(left_tree);
-            }
+    fn push_leftmost(&mut self, mut tree: BST<V, S>) {
+        while let NonEmpty(_) = tree {
+            let left_tree = tree.take_left().unwrap_or(Empty);
+            self.into_iter_stack.push(tree);
+            tree = left_tree;
         }
     }
 }
 
 /// Iterator for In-Order
 ///
-impl<V> Iterator for BSTInOrderIntoIterator<V>
+impl<V, S> Iterator for BSTInOrderIntoIterator<V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
 {
     type Item = V;
     // pop top of stack and return value, push left and then right nodes if they exist
@@ -97,14 +241,15 @@ where
 ///
 /// IntoIterator implementation
 ///
-impl<V> IntoIterator for BST<V>
+impl<V, S> IntoIterator for BST<V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
 {
     type Item = V;
-    type IntoIter = BSTInOrderIntoIterator<V>;
+    type IntoIter = BSTInOrderIntoIterator<V, S>;
 
-    fn into_iter(self) -> BSTInOrderIntoIterator<V> {
+    fn into_iter(self) -> BSTInOrderIntoIterator<V, S> {
         BSTInOrderIntoIterator::new(self)
     }
 }
@@ -112,29 +257,35 @@ where
 ///
 /// Post order iteration of BST.
 ///
-struct BSTPostIter<'a, V: 'a>
+pub struct BSTPostIter<'a, V: 'a, S: 'a = ()>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    iter_stack: Vec<(&'a BSTNode<V>, bool)>,
+    iter_stack: Vec<(&'a BSTNode<V, S>, bool)>,
 }
 
-impl<'a, V: 'a> BSTPostIter<'a, V>
+impl<'a, V: 'a, S: 'a> BSTPostIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    fn push_leftmost_thenright(&mut self, mut tree: &'a BST<V>) {
+    fn push_leftmost_thenright(&mut self, mut tree: &'a BST<V, S>) {
         while let NonEmpty(ref node) = *tree {
-            self.iter_stack.push((node, false));
-            // If left, set tree to left else if right, set tree to right
+            // If left, set tree to left else if right, set tree to right.
+            // The flag records whether the right subtree is already on the stack.
             match (&node.left, &node.right) {
-                (&Empty, &NonEmpty(_)) => tree = &node.right,
-                (_, _) => tree = &node.left,
+                (&Empty, &NonEmpty(_)) => {
+                    self.iter_stack.push((node, true));
+                    tree = &node.right;
+                }
+                (_, _) => {
+                    self.iter_stack.push((node, false));
+                    tree = &node.left;
+                }
             }
         }
     }
 
-    fn push_node(&mut self, node: &'a BSTNode<V>) {
+    fn push_node(&mut self, node: &'a BSTNode<V, S>) {
         self.iter_stack.push((node, true));
     }
 }
@@ -142,48 +293,39 @@ where
 ///
 /// Iterator for Post-Order
 ///
-impl<'a, V> Iterator for BSTPostIter<'a, V>
+impl<'a, V, S> Iterator for BSTPostIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
     type Item = &'a V;
     fn next(&mut self) -> Option<&'a V> {
-        let (node, _) = match self.iter_stack.pop() {
-            Some(n) => n,
-            None => return None,
-        };
-        match self.iter_stack.pop() {
-            Some((n, v)) => {
-                self.push_node(&n);
-                if !v {
-                    self.push_leftmost_thenright(&n.right);
-                }
-            }
-            None => return None,
+        let (node, _) = self.iter_stack.pop()?;
+        // Coming up from a left child, the parent's right subtree is next
+        if let Some(&(parent, false)) = self.iter_stack.last() {
+            self.iter_stack.pop();
+            self.push_node(parent);
+            self.push_leftmost_thenright(&parent.right);
         }
 
-        match node.val {
-            None => None,
-            Some(ref v) => Some(&v),
-        }
+        node.val.as_ref()
     }
 }
 
 ///
 /// Pre order iteration of BST.
 ///
-struct BSTPreIter<'a, V: 'a>
+pub struct BSTPreIter<'a, V: 'a, S: 'a = ()>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    iter_stack: Vec<&'a BSTNode<V>>,
+    iter_stack: Vec<&'a BSTNode<V, S>>,
 }
 
-impl<'a, V: 'a> BSTPreIter<'a, V>
+impl<'a, V: 'a, S: 'a> BSTPreIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    fn push_top(&mut self, tree: &'a BST<V>) {
+    fn push_top(&mut self, tree: &'a BST<V, S>) {
         if let NonEmpty(ref node) = *tree {
             self.iter_stack.push(node);
         }
@@ -193,17 +335,14 @@ where
 ///
 /// Iterator for Pre-Order
 ///
-impl<'a, V> Iterator for BSTPreIter<'a, V>
+impl<'a, V, S> Iterator for BSTPreIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
     type Item = &'a V;
     // pop top of stack and return value, push left and then right nodes if they exist
     fn next(&mut self) -> Option<&'a V> {
-        let node = match self.iter_stack.pop() {
-            Some(n) => n,
-            None => return None,
-        };
+        let node = self.iter_stack.pop()?;
 
         match (&node.left, &node.right) {
             (&NonEmpty(_), &NonEmpty(_)) => {
@@ -219,28 +358,25 @@ where
             (_, _) => {}
         }
 
-        match node.val {
-            None => None,
-            Some(ref v) => Some(&v),
-        }
+        node.val.as_ref()
     }
 }
 
 ///
 /// In order iteration of BST.
 ///
-struct BSTInOrderIter<'a, V: 'a>
+pub struct BSTInOrderIter<'a, V: 'a, S: 'a = ()>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    iter_stack: Vec<&'a BSTNode<V>>,
+    iter_stack: Vec<&'a BSTNode<V, S>>,
 }
 
-impl<'a, V: 'a> BSTInOrderIter<'a, V>
+impl<'a, V: 'a, S: 'a> BSTInOrderIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
-    fn push_leftmost(&mut self, mut tree: &'a BST<V>) {
+    fn push_leftmost(&mut self, mut tree: &'a BST<V, S>) {
         while let NonEmpty(ref node) = *tree {
             self.iter_stack.push(node);
             tree = &node.left;
@@ -251,39 +387,44 @@ where
 ///
 /// Iterator for In-Order
 ///
-impl<'a, V> Iterator for BSTInOrderIter<'a, V>
+impl<'a, V, S> Iterator for BSTInOrderIter<'a, V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
 {
     type Item = &'a V;
     // pop top of stack and return value, push left and then right nodes if they exist
     fn next(&mut self) -> Option<&'a V> {
-        let node = match self.iter_stack.pop() {
-            Some(n) => n,
-            None => return None,
-        };
+        let node = self.iter_stack.pop()?;
 
         self.push_leftmost(&node.right);
 
-        match node.val {
-            None => None,
-            Some(ref v) => Some(&v),
-        }
+        node.val.as_ref()
     }
 }
 
 //++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
 #[allow(dead_code)]
 #[allow(unused_variables)]
-impl<'a, V> BST<V>
+impl<V, S> BST<V, S>
 where
     V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
 {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Empty
     }
 
-    pub fn iter_post_order(&self) -> BSTPostIter<V> {
+    ///
+    /// Returns the summary of every value in the tree.
+    ///
+    pub fn summary(&self) -> S {
+        match *self {
+            Empty => S::identity(),
+            NonEmpty(ref n) => n.summary.clone(),
+        }
+    }
+
+    pub fn iter_post_order(&self) -> BSTPostIter<'_, V, S> {
         let mut iter = BSTPostIter {
             iter_stack: Vec::new(),
         };
@@ -292,7 +433,7 @@ where
     }
 
     // Pushed reference to top node
-    pub fn iter_pre_order(&self) -> BSTPreIter<V> {
+    pub fn iter_pre_order(&self) -> BSTPreIter<'_, V, S> {
         let mut iter = BSTPreIter {
             iter_stack: Vec::new(),
         };
@@ -300,7 +441,7 @@ where
         iter
     }
     // Pushed reference to top node
-    pub fn iter_in_order(&self) -> BSTInOrderIter<V> {
+    pub fn iter_in_order(&self) -> BSTInOrderIter<'_, V, S> {
         let mut iter = BSTInOrderIter {
             iter_stack: Vec::new(),
         };
@@ -308,89 +449,181 @@ where
         iter
     }
 
+    // A single node tree holding `val`
+    fn leaf(val: V) -> Self {
+        NonEmpty(Box::new(BSTNode {
+            left: Empty,
+            right: Empty,
+            summary: S::summarize(&val),
+            val: Some(val),
+            depth: 1,
+        }))
+    }
+
+    // Builds a balanced tree from strictly increasing values
+    fn from_sorted(values: &[V]) -> Self {
+        if values.is_empty() {
+            return Empty;
+        }
+        let mid = values.len() / 2;
+        let mut node = BSTNode {
+            left: BST::from_sorted(&values[..mid]),
+            right: BST::from_sorted(&values[mid + 1..]),
+            val: Some(values[mid]),
+            depth: 1,
+            summary: S::identity(),
+        };
+        node.update_summary();
+        NonEmpty(Box::new(node))
+    }
+
+    // Walks down as `choose` directs, unhooking every node it passes. Stops at the
+    // first empty slot or where `choose` says None, and returns what it stopped on.
+    fn unzip<F>(&mut self, mut choose: F) -> (Unzipped<V, S>, BST<V, S>)
+    where
+        F: FnMut(&BSTNode<V, S>) -> Option<Direction>,
+    {
+        let mut path = Vec::new();
+        let mut current = mem::replace(self, Empty);
+        loop {
+            let mut n = match current {
+                Empty => return (path, Empty),
+                NonEmpty(n) => n,
+            };
+            let dir = match choose(&n) {
+                None => return (path, NonEmpty(n)),
+                Some(dir) => dir,
+            };
+            current = match dir {
+                Direction::Left => mem::replace(&mut n.left, Empty),
+                Direction::Right => mem::replace(&mut n.right, Empty),
+            };
+            path.push((n, dir));
+        }
+    }
+
+    // Hooks `bottom` and the unhooked nodes back together, refreshing the summaries
+    // on the way up if anything below them changed
+    fn zip(&mut self, mut path: Unzipped<V, S>, bottom: BST<V, S>, changed: bool) {
+        let mut current = bottom;
+        while let Some((mut n, dir)) = path.pop() {
+            match dir {
+                Direction::Left => n.left = current,
+                Direction::Right => n.right = current,
+            }
+            if changed {
+                n.update_summary();
+            }
+            current = NonEmpty(n);
+        }
+        *self = current;
+    }
+
     /// https://gist.github.com/aidanhs  Binary Search Tree Tutorial
     /// Modified
-    pub fn insert(&mut self, new_val: V) {
-        match *self {
+    ///
+    /// Walks down without recursion, unhooking each node it passes. The nodes are
+    /// hooked back in on the way up, refreshing their summaries if a value was added.
+    ///
+    /// Returns false, leaving the tree unchanged, if `new_val` was already present.
+    ///
+    pub fn insert(&mut self, new_val: V) -> bool {
+        let (path, found) = self.unzip(|n| toward(n, &new_val));
+        match found {
             Empty => {
-                let new_tree = NonEmpty(Box::new(BSTNode {
-                    left: Empty,
-                    right: Empty,
-                    val: Some(new_val),
-                    depth: 1,
-                }));
-                *self = new_tree;
+                self.zip(path, BST::leaf(new_val), true);
+                true
+            }
+            // Already present, or a node without a value blocks the way
+            blocked => {
+                self.zip(path, blocked, false);
+                false
             }
-            NonEmpty(ref mut n) => match n.val {
-                None => return,
-                Some(v) => {
-                    if v == new_val {
-                        return;
-                    }
-                    let target_subtree = if v > new_val {
-                        &mut n.left
-                    } else {
-                        &mut n.right
-                    };
-                    match *target_subtree {
-                        NonEmpty(_) => target_subtree.insert(new_val),
-                        Empty => {
-                            let boxed_node = NonEmpty(Box::new(BSTNode {
-                                left: Empty,
-                                right: Empty,
-                                val: Some(new_val),
-                                depth: 1,
-                            }));
-                            *target_subtree = boxed_node;
-                        }
-                    }
-                }
-            },
         }
     }
 
     ///
-    /// Takes a reference to self and recursively explores left and right to find
-    /// taking the minimum of the two
+    /// Removes `val`, returning false if it was not in the tree.
+    ///
+    /// A node with one child is replaced by that child; a node with two takes over
+    /// the value of its in-order successor. Walks down and back up like insert.
+    ///
+    pub fn remove(&mut self, val: V) -> bool {
+        let (path, found) = self.unzip(|n| toward(n, &val));
+        let mut n = match found {
+            NonEmpty(n) if n.val.is_some() => n,
+            // Not there, or a node without a value blocks the way
+            missing => {
+                self.zip(path, missing, false);
+                return false;
+            }
+        };
+        let left = mem::replace(&mut n.left, Empty);
+        let right = mem::replace(&mut n.right, Empty);
+        let rest = match (left, right) {
+            (Empty, only) | (only, Empty) => only,
+            (left, mut right) => {
+                n.val = right.take_min();
+                n.left = left;
+                n.right = right;
+                n.update_summary();
+                NonEmpty(n)
+            }
+        };
+        self.zip(path, rest, true);
+        true
+    }
+
+    ///
+    /// Walks the tree level by level and returns the depth of the first leaf.
     ///
     pub fn min_depth(&self) -> u64 {
-        match *self {
-            Empty => 0,
-            NonEmpty(ref n) => match (&n.left, &n.right) {
-                (&Empty, &Empty) => 1,
-                (&Empty, &NonEmpty(_)) => n.right.min_depth() + 1,
-                (&NonEmpty(_), &Empty) => n.left.min_depth() + 1,
-
-                _ => cmp::min(n.right.min_depth(), n.left.min_depth()) + 1,
-            },
+        let mut level: Vec<&BSTNode<V, S>> = match *self {
+            Empty => return 0,
+            NonEmpty(ref n) => vec![n],
+        };
+        let mut depth = 1;
+        loop {
+            let mut next = Vec::new();
+            for n in level {
+                match (&n.left, &n.right) {
+                    (Empty, Empty) => return depth,
+                    (l, r) => {
+                        for child in &[l, r] {
+                            if let NonEmpty(ref c) = **child {
+                                next.push(&**c);
+                            }
+                        }
+                    }
+                }
+            }
+            level = next;
+            depth += 1;
         }
     }
 
     ///
-    ///Takes a reference to self and recursively explores left and right
-    ///always choosing branch with maximum depth.
+    /// Walks the tree level by level and returns the number of levels.
     ///
     pub fn height(&self) -> u64 {
-        match *self {
-            Empty => 0,
-            NonEmpty(ref n) => match (&n.left, &n.right) {
-                (&Empty, &Empty) => 1,
-                (&Empty, &NonEmpty(_)) => {
-                    println!("Current node: {:?}", n.val);
-                    n.right.height() + 1
-                }
-
-                (&NonEmpty(_), &Empty) => {
-                    println!("Current node: {:?}", n.val);
-                    n.left.height() + 1
-                }
-
-                _ => {
-                    println!("Current node: {:?}", n.val);
-                    cmp::max(n.left.height(), n.right.height()) + 1
+        let mut level: Vec<&BSTNode<V, S>> = match *self {
+            Empty => return 0,
+            NonEmpty(ref n) => vec![n],
+        };
+        let mut height = 0;
+        while !level.is_empty() {
+            height += 1;
+            let mut next = Vec::new();
+            for n in level {
+                for child in &[&n.left, &n.right] {
+                    if let NonEmpty(ref c) = **child {
+                        next.push(&**c);
+                    }
                 }
-            },
+            }
+            level = next;
         }
+        height
     }
 
     ///
@@ -404,54 +637,57 @@ where
     /// returns an option of generic type V. None or Some(V).
     ///
     pub fn find(&self, val: V) -> Option<V> {
-        match *self {
-            Empty => None,
-            NonEmpty(ref n) => {
-                if n.val == Some(val) {
-                    Some(val)
-                } else if n.val > Some(val) {
-                    n.left.find(val)
-                } else {
-                    n.right.find(val)
-                }
+        let mut tree = self;
+        while let NonEmpty(ref n) = *tree {
+            if n.val == Some(val) {
+                return Some(val);
+            } else if n.val > Some(val) {
+                tree = &n.left;
+            } else {
+                tree = &n.right;
             }
         }
+        None
     }
 
     ///
     /// If tree contains generic type V. Returns true. Otherwise returns false.
     ///
     pub fn contains(&self, val: V) -> bool {
-        match *self {
-            Empty => false,
-            NonEmpty(ref n) => {
-                if n.val == Some(val) {
-                    true
-                } else if n.val > Some(val) {
-                    n.left.contains(val)
-                } else {
-                    n.right.contains(val)
-                }
-            }
-        }
+        self.find(val).is_some()
     }
 
     ///
     /// Returns tree's minimum value
     ///
     pub fn min_value(&self) -> Option<V> {
-        match *self {
-            Empty => None,
-            NonEmpty(ref n) => match n.left {
-                Empty => n.val,
-                NonEmpty(_) => n.left.min_value(),
-            },
+        let mut n = match *self {
+            Empty => return None,
+            NonEmpty(ref n) => n,
+        };
+        while let NonEmpty(ref l) = n.left {
+            n = l;
+        }
+        n.val
+    }
+
+    ///
+    /// Returns tree's maximum value
+    ///
+    pub fn max_value(&self) -> Option<V> {
+        let mut n = match *self {
+            Empty => return None,
+            NonEmpty(ref n) => n,
+        };
+        while let NonEmpty(ref r) = n.right {
+            n = r;
         }
+        n.val
     }
 
     // Swap values of the current BST with the right node BST
     // Return the current BST
-    pub fn swap_right(&mut self) -> Option<&BST<V>> {
+    pub fn swap_right(&mut self) -> Option<&BST<V, S>> {
         let mut curr_val: Option<V> = None;
         let mut temp_val: Option<V> = None;
 
@@ -464,9 +700,11 @@ where
                     NonEmpty(ref mut r) => {
                         mem::swap(&mut temp_val, &mut r.val);
                         mem::swap(&mut curr_val, &mut r.val);
+                        r.update_summary();
                     }
                 };
                 mem::swap(&mut temp_val, &mut node.val);
+                node.update_summary();
             }
         };
 
@@ -475,7 +713,7 @@ where
 
     // Swap values of the current BST with the left node BST
     // Return the current BST
-    pub fn swap_left(&mut self) -> Option<&BST<V>> {
+    pub fn swap_left(&mut self) -> Option<&BST<V, S>> {
         let mut curr_val: Option<V> = None;
         let mut temp_val: Option<V> = None;
 
@@ -488,9 +726,11 @@ where
                     NonEmpty(ref mut r) => {
                         mem::swap(&mut temp_val, &mut r.val);
                         mem::swap(&mut curr_val, &mut r.val);
+                        r.update_summary();
                     }
                 };
                 mem::swap(&mut temp_val, &mut node.val);
+                node.update_summary();
             }
         };
 
@@ -498,33 +738,55 @@ where
     }
 
     ///
+    /// Removes the right subtree and returns it.
     ///
-    pub fn take_right(&mut self) -> Option<BST<V>> {
+    pub fn take_right(&mut self) -> Option<BST<V, S>> {
         match *self {
             Empty => None,
             NonEmpty(ref mut tree) => {
-                let mut right: BST<V> = Empty;
+                let mut right: BST<V, S> = Empty;
                 mem::swap(&mut tree.right, &mut right);
+                tree.update_summary();
                 Some(right)
             }
         }
     }
 
-    pub fn take_left(&mut self) -> Option<BST<V>> {
+    ///
+    /// Removes the left subtree and returns it.
+    ///
+    pub fn take_left(&mut self) -> Option<BST<V, S>> {
         match *self {
             Empty => None,
             NonEmpty(ref mut tree) => {
-                let mut left: BST<V> = Empty;
+                let mut left: BST<V, S> = Empty;
                 mem::swap(&mut tree.left, &mut left);
+                tree.update_summary();
                 Some(left)
             }
         }
     }
 
+    // Removes the leftmost node of the tree and returns its value
+    fn take_min(&mut self) -> Option<V> {
+        let (path, found) = self.unzip(|n| match n.left {
+            NonEmpty(_) => Some(Direction::Left),
+            Empty => None,
+        });
+        // Only an empty tree stops on an empty slot, and then there is no path
+        let mut n = match found {
+            NonEmpty(n) => n,
+            Empty => return None,
+        };
+        let rest = mem::replace(&mut n.right, Empty);
+        self.zip(path, rest, true);
+        n.val
+    }
+
     ///
     /// Merges current tree with another tree by inserting values from other.
     ///
-    pub fn merge(&mut self, other_tree: BST<V>) {
+    pub fn merge(&mut self, other_tree: BST<V, S>) {
         for n in other_tree.iter_in_order() {
             self.insert(*n);
         }
@@ -533,19 +795,57 @@ where
     ///
     /// Gets the value the root.
     ///
-    fn peek(&self) -> Option<&V> {
+    pub fn peek(&self) -> Option<&V> {
         match *self {
             Empty => None,
-            NonEmpty(ref n) => match n.val {
-                None => None,
-                Some(ref v) => Some(&v),
-            },
+            NonEmpty(ref n) => n.val.as_ref(),
         }
     }
 }
 
+///
+/// Builds a balanced tree from any values, dropping duplicates.
+///
+impl<V, S> FromIterator<V> for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        let mut values: Vec<V> = iter.into_iter().collect();
+        values.sort();
+        values.dedup();
+        BST::from_sorted(&values)
+    }
+}
+
 //++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
 
+///
+/// The values the module tests build their trees from, in insertion order.
+/// Inserted into a BST they give
+///
+///         8
+///       /   \
+///      6     13
+///     / \   /  \
+///    1   7 10   20
+///
+#[cfg(test)]
+const SAMPLE: [i32; 7] = [8, 13, 6, 1, 20, 10, 7];
+
+///
+/// The tree most of the module tests start from, SAMPLE inserted in order.
+///
+#[cfg(test)]
+fn sample_tree<S: Summary<i32>>() -> BST<i32, S> {
+    let mut tree = BST::new();
+    for v in &SAMPLE {
+        tree.insert(*v);
+    }
+    tree
+}
+
 #[test]
 fn add_node() {
     let mut tree: BST<i32> = BST::new();
@@ -577,7 +877,16 @@ fn post_iterator_test() {
         for node in tree.iter_post_order() {
             assert_eq!(node, &node_vec.pop().unwrap());
         }
+        assert!(node_vec.is_empty());
     }
+
+    // Nodes with only a right child
+    let mut chain: BST<i32> = BST::new();
+    for v in &[1, 3, 2, 4] {
+        chain.insert(*v);
+    }
+    let post: Vec<i32> = chain.iter_post_order().cloned().collect();
+    assert_eq!(post, vec![2, 4, 3, 1]);
 }
 #[test]
 fn pre_iterator_test() {
@@ -639,6 +948,7 @@ fn in_order_into_iterator_test() {
     for node in tree_iter {
         assert_eq!(node, node_vec.pop().unwrap());
     }
+    assert!(node_vec.is_empty());
 }
 #[test]
 fn swap_right_test() {
@@ -810,6 +1120,7 @@ fn find_test() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn contains_test() {
     let mut tree: BST<i32> = BST::new();
 
@@ -882,3 +1193,85 @@ fn min_value_test() {
     //left
     assert_eq!(tree.min_value(), Some(1));
 }
+
+#[test]
+fn remove_test() {
+    let mut tree: BST<i32, Sum<i32>> = BST::new();
+
+    tree.insert(8);
+    tree.insert(13);
+    tree.insert(6);
+    tree.insert(1);
+    tree.insert(20);
+    tree.insert(10);
+    tree.insert(7);
+    tree.insert(25);
+
+    //leaf, one child, two children
+    assert!(tree.remove(7));
+    assert!(tree.remove(20));
+    assert!(tree.remove(8));
+    assert!(!tree.remove(8));
+    assert!(!tree.remove(99));
+
+    let pre_vec: Vec<i32> = tree.iter_pre_order().cloned().collect();
+    assert_eq!(pre_vec, vec![10, 6, 1, 13, 25]);
+    assert_eq!(tree.summary(), Sum(55));
+
+    for v in &[1, 6, 10, 13, 25] {
+        assert!(tree.remove(*v));
+    }
+    assert_eq!(tree.size(), 0);
+}
+
+#[test]
+fn from_iter_test() {
+    let tree: BST<i32> = vec![20, 1, 13, 6, 8, 10, 7, 13].into_iter().collect();
+
+    let mut pre_vec: Vec<i32> = vec![8, 6, 1, 7, 13, 10, 20];
+    pre_vec.reverse();
+    for node in tree.iter_pre_order() {
+        assert_eq!(node, &pre_vec.pop().unwrap());
+    }
+    assert_eq!(tree.height(), 3);
+}
+
+#[test]
+fn million_node_chain_test() {
+    // Built by hand, inserting a sorted million values one by one would take O(n^2)
+    let n: u32 = 1_000_000;
+    let mut tree: BST<u32> = BST::new();
+    for v in (0..n).rev() {
+        tree = NonEmpty(Box::new(BSTNode {
+            val: Some(v),
+            left: Empty,
+            right: tree,
+            depth: 1,
+            summary: (),
+        }));
+    }
+    assert_eq!(tree.height(), n as u64);
+    assert_eq!(tree.min_depth(), n as u64);
+    assert_eq!(tree.find(n - 1), Some(n - 1));
+    assert!(!tree.contains(n));
+    assert_eq!(tree.min_value(), Some(0));
+
+    tree.insert(n);
+    assert_eq!(tree.height(), n as u64 + 1);
+    assert_eq!(tree.size(), n as usize + 1);
+    drop(tree);
+
+    // The same chain hanging to the left
+    let mut tree: BST<u32> = BST::new();
+    for v in 0..n {
+        tree = NonEmpty(Box::new(BSTNode {
+            val: Some(v),
+            left: tree,
+            right: Empty,
+            depth: 1,
+            summary: (),
+        }));
+    }
+    assert_eq!(tree.min_value(), Some(0));
+    assert_eq!(tree.into_iter().take(3).collect::<Vec<u32>>(), vec![0, 1, 2]);
+}
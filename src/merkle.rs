@@ -0,0 +1,471 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Pluggable hash functions for the Merkle hashes of an ObjTree
+//
+//
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+// Prefixes keeping object hashes and tree hashes from ever being confused
+const OBJ_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+
+///
+/// The bytes a value is hashed as.
+///
+/// Unlike `std::hash::Hash`, which leaves the byte layout to the platform and
+/// the Rust release, the encoding is fixed: integers are little endian, `usize`
+/// and `isize` are widened to 64 bits, and values of varying length are prefixed
+/// with their length as a u64. Implementations for other types should follow
+/// the same rules, so equal values give equal bytes everywhere.
+///
+pub trait HashBytes {
+    fn hash_bytes(&self, out: &mut Vec<u8>);
+}
+
+macro_rules! int_hash_bytes {
+    ($($t:ty),*) => {$(
+        impl HashBytes for $t {
+            fn hash_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+int_hash_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl HashBytes for usize {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        (*self as u64).hash_bytes(out);
+    }
+}
+
+impl HashBytes for isize {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        (*self as i64).hash_bytes(out);
+    }
+}
+
+impl HashBytes for bool {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        (*self as u8).hash_bytes(out);
+    }
+}
+
+impl HashBytes for char {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        (*self as u32).hash_bytes(out);
+    }
+}
+
+impl HashBytes for str {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        self.len().hash_bytes(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl HashBytes for String {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        self.as_str().hash_bytes(out);
+    }
+}
+
+impl<T: HashBytes> HashBytes for [T] {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        self.len().hash_bytes(out);
+        for item in self {
+            item.hash_bytes(out);
+        }
+    }
+}
+
+impl<T: HashBytes> HashBytes for Vec<T> {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        self[..].hash_bytes(out);
+    }
+}
+
+impl<T: HashBytes> HashBytes for Option<T> {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        match *self {
+            None => out.push(0),
+            Some(ref v) => {
+                out.push(1);
+                v.hash_bytes(out);
+            }
+        }
+    }
+}
+
+impl<A: HashBytes, B: HashBytes> HashBytes for (A, B) {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        self.0.hash_bytes(out);
+        self.1.hash_bytes(out);
+    }
+}
+
+impl<T: HashBytes + ?Sized> HashBytes for &T {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        (**self).hash_bytes(out);
+    }
+}
+
+///
+/// Fixed size byte arrays, such as wide digests, are hashed as they are.
+///
+impl<const N: usize> HashBytes for [u8; N] {
+    fn hash_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+///
+/// A streaming hash state, fed raw bytes.
+///
+/// Reading the digest out is left to the TreeHasher, so a state can be as wide
+/// as its digest instead of reducing itself to the u64 that
+/// `std::hash::Hasher::finish` asks for.
+///
+pub trait HashState {
+    fn update(&mut self, bytes: &[u8]);
+}
+
+///
+/// A hash function for ObjTree.
+///
+/// An implementation only supplies a streaming state and the step turning it into
+/// a digest; `Digest` sets the width, from the default u64 up to a [u8; 32] for
+/// SHA-256 or BLAKE3 style functions.
+///
+/// The provided methods define the Merkle scheme: a node's tree hash covers its
+/// object hash, its number of children and their tree hashes in order. They lay
+/// everything out with `HashBytes` and only ever call `update` on the state, so
+/// a digest depends on nothing but the hash function itself.
+///
+pub trait TreeHasher {
+    type Digest: Clone + Debug + Eq + Hash + HashBytes;
+    type State: HashState;
+
+    fn new_state() -> Self::State;
+
+    fn digest(state: Self::State) -> Self::Digest;
+
+    fn hash_obj<V: HashBytes + ?Sized>(obj: &V) -> Self::Digest {
+        let mut bytes = vec![OBJ_TAG];
+        obj.hash_bytes(&mut bytes);
+        let mut state = Self::new_state();
+        state.update(&bytes);
+        Self::digest(state)
+    }
+
+    fn hash_node(obj_hash: &Self::Digest, children: &[Self::Digest]) -> Self::Digest {
+        let mut bytes = vec![NODE_TAG];
+        obj_hash.hash_bytes(&mut bytes);
+        children.hash_bytes(&mut bytes);
+        let mut state = Self::new_state();
+        state.update(&bytes);
+        Self::digest(state)
+    }
+}
+
+///
+/// The default TreeHasher: SipHash-2-4 keyed with zeros, giving a u64 digest.
+/// Fast, but not cryptographic, so it only guards against accidents.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SipTreeHasher;
+
+impl TreeHasher for SipTreeHasher {
+    type Digest = u64;
+    type State = SipState;
+
+    fn new_state() -> SipState {
+        SipState::with_keys(0, 0)
+    }
+
+    fn digest(state: SipState) -> u64 {
+        state.finish()
+    }
+}
+
+///
+/// SipHash-2-4 as specified by Aumasson and Bernstein, the state behind
+/// SipTreeHasher. Unlike std's `DefaultHasher`, whose algorithm may change
+/// between releases, its output is fixed.
+///
+#[derive(Debug, Clone)]
+pub struct SipState {
+    v: [u64; 4],
+    // Bytes not yet making up a full word, little endian
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl SipState {
+    fn with_keys(k0: u64, k1: u64) -> Self {
+        SipState {
+            v: [
+                k0 ^ 0x736f_6d65_7073_6575,
+                k1 ^ 0x646f_7261_6e64_6f6d,
+                k0 ^ 0x6c79_6765_6e65_7261,
+                k1 ^ 0x7465_6462_7974_6573,
+            ],
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        let v = &mut self.v;
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v[3] ^= word;
+        self.round();
+        self.round();
+        self.v[0] ^= word;
+    }
+
+    ///
+    /// The 64 bit SipHash of the bytes so far. The state can take more bytes
+    /// afterwards.
+    ///
+    pub fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        state.compress(((self.length as u64 & 0xff) << 56) | self.tail);
+        state.v[2] ^= 0xff;
+        for _ in 0..4 {
+            state.round();
+        }
+        state.v[0] ^ state.v[1] ^ state.v[2] ^ state.v[3]
+    }
+}
+
+impl HashState for SipState {
+    fn update(&mut self, bytes: &[u8]) {
+        self.length += bytes.len();
+        for b in bytes {
+            self.tail |= u64::from(*b) << (8 * self.ntail);
+            self.ntail += 1;
+            if self.ntail == 8 {
+                let word = self.tail;
+                self.compress(word);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use std::hash::Hasher;
+
+#[cfg(test)]
+use super::{NodeId, ObjTree};
+
+// A 32 byte digest from four differently seeded FNV-1a lanes, standing in for a
+// real cryptographic hash
+#[cfg(test)]
+pub struct WideHasher;
+
+#[cfg(test)]
+pub struct WideState([u64; 4]);
+
+#[cfg(test)]
+impl HashState for WideState {
+    fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            for (i, lane) in self.0.iter_mut().enumerate() {
+                *lane = (*lane ^ u64::from(*b)).wrapping_mul(0x100_0000_01b3 + 2 * i as u64);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl TreeHasher for WideHasher {
+    type Digest = [u8; 32];
+    type State = WideState;
+
+    fn new_state() -> WideState {
+        WideState([
+            0xcbf2_9ce4_8422_2325,
+            0x6c62_272e_07bb_0142,
+            0x8422_2325_cbf2_9ce4,
+            0x07bb_0142_6c62_272e,
+        ])
+    }
+
+    fn digest(state: WideState) -> [u8; 32] {
+        let mut out = [0; 32];
+        for (chunk, lane) in out.chunks_mut(8).zip(state.0.iter()) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        out
+    }
+}
+
+// 1 has children 2 and 3, and 2 has children 4 and 5
+#[cfg(test)]
+fn merkle_sample<H: TreeHasher>() -> (ObjTree<i32, H>, Vec<NodeId>) {
+    let mut tree: ObjTree<i32, H> = ObjTree::with_hasher(1);
+    let root = tree.root();
+    let two = tree.add(root, ObjTree::with_hasher(2)).unwrap();
+    let three = tree.add(root, ObjTree::with_hasher(3)).unwrap();
+    let four = tree.add(two, ObjTree::with_hasher(4)).unwrap();
+    let five = tree.add(two, ObjTree::with_hasher(5)).unwrap();
+    (tree, vec![root, two, three, four, five])
+}
+
+// Recomputes every tree hash from scratch, bottom up
+#[cfg(test)]
+fn full_rehash<H: TreeHasher>(tree: &ObjTree<i32, H>, id: NodeId) -> H::Digest {
+    let children: Vec<H::Digest> = tree
+        .children(id)
+        .unwrap()
+        .iter()
+        .map(|c| full_rehash(tree, *c))
+        .collect();
+    H::hash_node(&H::hash_obj(tree.get(id).unwrap()), &children)
+}
+
+#[test]
+fn tree_hash_covers_the_subtree() {
+    let (tree, ids) = merkle_sample::<SipTreeHasher>();
+    let leaf = SipTreeHasher::hash_node(&SipTreeHasher::hash_obj(&4), &[]);
+    assert_eq!(tree.get_tree_hash(ids[3]).unwrap().0, leaf);
+    for id in &ids {
+        assert_eq!(tree.get_tree_hash(*id).unwrap().0, full_rehash(&tree, *id));
+    }
+    assert_eq!(tree.root_hash().0, full_rehash(&tree, ids[0]));
+}
+
+#[test]
+fn hashes_recomputed_up_the_ancestors() {
+    let (mut tree, ids) = merkle_sample::<SipTreeHasher>();
+    let before = *tree.root_hash();
+    let three = *tree.get_tree_hash(ids[2]).unwrap();
+
+    // a change deep down reaches the root but not the other branch
+    let six = tree.add(ids[4], ObjTree::new(6)).unwrap();
+    assert!(*tree.root_hash() != before);
+    assert_eq!(*tree.get_tree_hash(ids[2]).unwrap(), three);
+    assert_eq!(tree.root_hash().0, full_rehash(&tree, ids[0]));
+
+    // taking it out again restores every hash
+    let sub = tree.split(six).unwrap();
+    assert_eq!(*tree.root_hash(), before);
+    assert_eq!(sub.root_hash().0, full_rehash(&sub, sub.root()));
+}
+
+#[test]
+fn order_and_shape_change_the_hash() {
+    let (a, ids) = merkle_sample::<SipTreeHasher>();
+
+    // same objects with 2 and 3 swapped
+    let mut b: ObjTree<i32> = ObjTree::new(1);
+    let root = b.root();
+    b.add(root, ObjTree::new(3)).unwrap();
+    let two = b.insert_child(root, 1, ObjTree::new(2)).unwrap();
+    b.add(two, ObjTree::new(4)).unwrap();
+    b.add(two, ObjTree::new(5)).unwrap();
+    assert!(a.root_hash() != b.root_hash());
+    assert_eq!(a.get_tree_hash(ids[1]), b.get_tree_hash(two));
+
+    // same objects, 5 moved under 4
+    let mut c: ObjTree<i32> = ObjTree::new(1);
+    let root = c.root();
+    let two = c.add(root, ObjTree::new(2)).unwrap();
+    c.add(root, ObjTree::new(3)).unwrap();
+    let four = c.add(two, ObjTree::new(4)).unwrap();
+    c.add(four, ObjTree::new(5)).unwrap();
+    assert!(a.root_hash() != c.root_hash());
+}
+
+#[test]
+fn pluggable_wide_digest() {
+    let (mut tree, ids) = merkle_sample::<WideHasher>();
+    let before = *tree.root_hash();
+    assert_eq!(before.0.len(), 32);
+    assert_eq!(tree.root_hash().0, full_rehash(&tree, ids[0]));
+    tree.split(ids[3]).unwrap();
+    assert!(*tree.root_hash() != before);
+    assert_eq!(tree.root_hash().0, full_rehash(&tree, ids[0]));
+}
+
+#[test]
+fn sip_state_is_siphash_2_4() {
+    // The example from the SipHash paper, key 00..0f over message 00..0e
+    let mut state = SipState::with_keys(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+    state.update(&(0..15).collect::<Vec<u8>>());
+    assert_eq!(state.finish(), 0xa129_ca61_49be_45e5);
+
+    // and std's SipHash-2-4 for every length across a few words, fed in pieces
+    let bytes: Vec<u8> = (0..40u8).map(|b| b.wrapping_mul(37)).collect();
+    for len in 0..bytes.len() {
+        #[allow(deprecated)]
+        let mut reference = std::hash::SipHasher::new_with_keys(1, 2);
+        reference.write(&bytes[..len]);
+        let mut state = SipState::with_keys(1, 2);
+        let (head, tail) = bytes[..len].split_at(len / 3);
+        state.update(head);
+        state.update(tail);
+        assert_eq!(state.finish(), reference.finish());
+    }
+}
+
+#[test]
+fn default_digests_are_pinned() {
+    // These change only if the hashing scheme itself does
+    let obj = SipTreeHasher::hash_obj(&1i32);
+    assert_eq!(obj, 0x7096_5a7b_1b6e_6031);
+    assert_eq!(SipTreeHasher::hash_node(&obj, &[]), 0x9d97_2b66_29fe_dcfb);
+    assert_eq!(SipTreeHasher::hash_obj("one"), 0x638f_d4c0_d3e0_8c95);
+}
+
+#[test]
+fn hash_bytes_layout() {
+    let bytes = |v: &dyn Fn(&mut Vec<u8>)| {
+        let mut out = Vec::new();
+        v(&mut out);
+        out
+    };
+    assert_eq!(bytes(&|o| 258u16.hash_bytes(o)), vec![2, 1]);
+    assert_eq!(
+        bytes(&|o| 7usize.hash_bytes(o)),
+        bytes(&|o| 7u64.hash_bytes(o))
+    );
+    assert_eq!(bytes(&|o| (-1isize).hash_bytes(o)), vec![0xff; 8]);
+    assert_eq!(
+        bytes(&|o| "ab".hash_bytes(o)),
+        vec![2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']
+    );
+    assert_eq!(
+        bytes(&|o| "ab".to_string().hash_bytes(o)),
+        bytes(&|o| "ab".hash_bytes(o))
+    );
+    assert_eq!(
+        bytes(&|o| vec![1u8, 2].hash_bytes(o)),
+        bytes(&|o| "\u{1}\u{2}".hash_bytes(o))
+    );
+    assert_eq!(bytes(&|o| [1u8, 2].hash_bytes(o)), vec![1, 2]);
+    assert_eq!(bytes(&|o| Some(3u8).hash_bytes(o)), vec![1, 3]);
+    assert_eq!(bytes(&|o| None::<u8>.hash_bytes(o)), vec![0]);
+    assert_eq!(bytes(&|o| (1u8, 'a').hash_bytes(o)), vec![1, b'a', 0, 0, 0]);
+}
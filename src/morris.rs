@@ -0,0 +1,272 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// In-order traversal in constant extra space
+//
+//
+
+use std::fmt::Debug;
+use std::mem;
+
+use super::BST::*;
+use super::{Summary, BST};
+
+// Marks kept in the otherwise unused `BSTNode::depth` of nodes on the reversed
+// path, telling which child link currently points back up
+const CAME_FROM_LEFT: usize = 1;
+const CAME_FROM_RIGHT: usize = 0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    // Walk down the left spine of `current`
+    Down,
+    // `current`'s own value was produced, its right subtree is next
+    AtNode,
+    // `current` is finished, climb back up
+    Up,
+    Done,
+}
+
+///
+/// In-order iterator over `&mut BST` using O(1) auxiliary memory.
+///
+/// Morris traversal threads the tree through shared pointers, which `Box` links
+/// cannot express, so this uses the owned equivalent: link reversal. On the way
+/// down each child link is turned around to point back at its parent, and on the
+/// way up it is turned back. The only bookkeeping is one mark per node on the
+/// current path, kept in a spare field of the node itself, so nothing grows with
+/// the height of the tree.
+///
+/// The tree is restored exactly, summaries included, when the iterator is
+/// exhausted or dropped. Leaking the iterator with `mem::forget` leaves the tree
+/// empty.
+///
+pub struct MorrisIter<'a, V: 'a, S: 'a = ()>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    tree: &'a mut BST<V, S>,
+    // The subtree being walked; always intact
+    current: BST<V, S>,
+    // Parent of `current`, its link towards `current` pointing to the grandparent
+    back: BST<V, S>,
+    state: State,
+}
+
+impl<'a, V, S> MorrisIter<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    // Steps from `current` into one of its children, reversing the link
+    fn descend(&mut self, mark: usize) {
+        let mut node = match mem::replace(&mut self.current, Empty) {
+            NonEmpty(n) => n,
+            Empty => return,
+        };
+        let parent = mem::replace(&mut self.back, Empty);
+        self.current = if mark == CAME_FROM_LEFT {
+            mem::replace(&mut node.left, parent)
+        } else {
+            mem::replace(&mut node.right, parent)
+        };
+        node.depth = mark;
+        self.back = NonEmpty(node);
+    }
+
+    // Steps from `current` back to its parent, restoring the link.
+    // Returns the mark saying which child `current` was.
+    fn ascend(&mut self) -> Option<usize> {
+        let mut node = match mem::replace(&mut self.back, Empty) {
+            NonEmpty(n) => n,
+            Empty => return None,
+        };
+        let child = mem::replace(&mut self.current, Empty);
+        let mark = node.depth;
+        self.back = if mark == CAME_FROM_LEFT {
+            mem::replace(&mut node.left, child)
+        } else {
+            mem::replace(&mut node.right, child)
+        };
+        node.depth = 1;
+        self.current = NonEmpty(node);
+        Some(mark)
+    }
+
+    fn has_child(&self, left: bool) -> bool {
+        match self.current {
+            NonEmpty(ref n) if left => !matches!(n.left, Empty),
+            NonEmpty(ref n) => !matches!(n.right, Empty),
+            Empty => false,
+        }
+    }
+
+    fn value(&self) -> Option<V> {
+        match self.current {
+            NonEmpty(ref n) => n.val,
+            Empty => None,
+        }
+    }
+
+    // Climbs to the root and hands the tree back
+    fn finish(&mut self) {
+        while self.ascend().is_some() {}
+        *self.tree = mem::replace(&mut self.current, Empty);
+        self.state = State::Done;
+    }
+}
+
+impl<'a, V, S> Iterator for MorrisIter<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        loop {
+            match self.state {
+                State::Down => {
+                    while self.has_child(true) {
+                        self.descend(CAME_FROM_LEFT);
+                    }
+                    if let Empty = self.current {
+                        self.state = State::Up;
+                        continue;
+                    }
+                    self.state = State::AtNode;
+                    // Nodes that lost their value to a swap are passed over
+                    if let Some(v) = self.value() {
+                        return Some(v);
+                    }
+                }
+                State::AtNode => {
+                    if self.has_child(false) {
+                        self.descend(CAME_FROM_RIGHT);
+                        self.state = State::Down;
+                    } else {
+                        self.state = State::Up;
+                    }
+                }
+                State::Up => match self.ascend() {
+                    None => {
+                        self.finish();
+                        return None;
+                    }
+                    Some(CAME_FROM_LEFT) => {
+                        self.state = State::AtNode;
+                        if let Some(v) = self.value() {
+                            return Some(v);
+                        }
+                    }
+                    Some(_) => (),
+                },
+                State::Done => return None,
+            }
+        }
+    }
+}
+
+impl<'a, V, S> Drop for MorrisIter<'a, V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn drop(&mut self) {
+        if self.state != State::Done {
+            self.finish();
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// In-order iteration without a stack, see `MorrisIter`. Takes `&mut self`
+    /// because the links are rearranged while the walk is under way.
+    ///
+    pub fn morris_iter(&mut self) -> MorrisIter<'_, V, S> {
+        MorrisIter {
+            current: mem::replace(self, Empty),
+            tree: self,
+            back: Empty,
+            state: State::Down,
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, BSTNode, Sum};
+
+#[test]
+fn morris_matches_in_order() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let pre: Vec<i32> = tree.iter_pre_order().cloned().collect();
+    let expected: Vec<i32> = tree.iter_in_order().cloned().collect();
+
+    assert_eq!(tree.morris_iter().collect::<Vec<i32>>(), expected);
+    // shape and summaries are back as they were
+    assert_eq!(tree.iter_pre_order().cloned().collect::<Vec<i32>>(), pre);
+    assert_eq!(tree.summary(), Sum(87));
+    assert_eq!(tree.aggregate(7..12), Sum(45));
+
+    let mut empty: BST<i32> = BST::new();
+    assert_eq!(empty.morris_iter().next(), None);
+}
+
+#[test]
+fn morris_restores_after_early_drop() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let pre: Vec<i32> = tree.iter_pre_order().cloned().collect();
+    for stop in 0..12 {
+        let taken: Vec<i32> = tree.morris_iter().take(stop).collect();
+        assert_eq!(taken.len(), stop.min(10));
+        assert_eq!(tree.iter_pre_order().cloned().collect::<Vec<i32>>(), pre);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn morris_on_broken_order() {
+    // The walk follows links, not values, so swapped trees come back intact too
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    tree.swap_right();
+    let expected: Vec<i32> = tree.iter_in_order().cloned().collect();
+    let pre: Vec<i32> = tree.iter_pre_order().cloned().collect();
+    assert_eq!(tree.morris_iter().collect::<Vec<i32>>(), expected);
+    assert_eq!(tree.iter_pre_order().cloned().collect::<Vec<i32>>(), pre);
+}
+
+#[test]
+fn morris_deep_chains() {
+    let n: u32 = 200_000;
+    let mut tree: BST<u32> = BST::new();
+    for v in 0..n {
+        // each new value becomes the root with the old tree on its left
+        tree = NonEmpty(Box::new(BSTNode {
+            val: Some(v),
+            left: tree,
+            right: Empty,
+            depth: 1,
+            summary: (),
+        }));
+    }
+    assert!(tree.morris_iter().eq(0..n));
+    assert_eq!(tree.morris_iter().nth(1000), Some(1000));
+    assert_eq!(tree.height(), n as u64);
+    assert_eq!(tree.peek(), Some(&(n - 1)));
+}
@@ -0,0 +1,828 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Hash-guided diff and patching of ObjTree replicas
+//
+//
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
+
+use super::binary::Checksummed;
+use super::{Codec, FormatError, HashBytes, NodeId, ObjHash, ObjTree, SipTreeHasher, TreeHasher};
+
+///
+/// Change layout, integers little endian as in the tree file format:
+///
+///   magic     4 bytes  "GTCH"
+///   version   u16  CHANGE_FORMAT_VERSION
+///   kind      u8   0 added, 1 removed, 2 modified
+///   path      u64 count, then a u64 child index per level
+///   then for an addition, the subtree:
+///             u64 node count, then per node in pre-order its u64 child
+///             count and its object
+///   or for a modification, the new object
+///   checksum  u32  FNV-1a of every byte before it
+///
+/// Objects are written by their Codec. Hashes are not stored, the subtree is
+/// rehashed when read.
+///
+pub const CHANGE_MAGIC: [u8; 4] = *b"GTCH";
+
+///
+/// Version of the change layout, bumped whenever the layout above changes.
+///
+pub const CHANGE_FORMAT_VERSION: u16 = 2;
+
+const ADDED: u8 = 0;
+const REMOVED: u8 = 1;
+const MODIFIED: u8 = 2;
+
+///
+/// One edit taking an ObjTree towards another, as produced by `diff`.
+///
+/// Paths give a child index per level from the root, as in `node_at`, and refer to
+/// the tree as it stands once the changes before this one are applied.
+///
+pub enum TreeChange<V, H = SipTreeHasher>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    ///
+    /// `subtree` is inserted so that its root ends up at `path`.
+    ///
+    Added {
+        path: Vec<usize>,
+        subtree: ObjTree<V, H>,
+    },
+    ///
+    /// The subtree at `path` is taken out.
+    ///
+    Removed { path: Vec<usize> },
+    ///
+    /// The object at `path` becomes `obj`; its children are left alone.
+    ///
+    Modified { path: Vec<usize>, obj: V },
+}
+
+impl<V, H> TreeChange<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    pub fn path(&self) -> &[usize] {
+        match *self {
+            TreeChange::Added { ref path, .. }
+            | TreeChange::Removed { ref path }
+            | TreeChange::Modified { ref path, .. } => path,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        V: Codec,
+    {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("writing to a Vec cannot fail");
+        out
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> Result<(), FormatError>
+    where
+        V: Codec,
+    {
+        let mut body = Checksummed::new(&mut *w);
+        body.write_all(&CHANGE_MAGIC)?;
+        CHANGE_FORMAT_VERSION.encode(&mut body)?;
+        let kind = match *self {
+            TreeChange::Added { .. } => ADDED,
+            TreeChange::Removed { .. } => REMOVED,
+            TreeChange::Modified { .. } => MODIFIED,
+        };
+        kind.encode(&mut body)?;
+        let path = self.path();
+        path.len().encode(&mut body)?;
+        for index in path {
+            index.encode(&mut body)?;
+        }
+        match *self {
+            TreeChange::Added { ref subtree, .. } => write_subtree(subtree, &mut body)?,
+            TreeChange::Removed { .. } => {}
+            TreeChange::Modified { ref obj, .. } => obj.encode(&mut body)?,
+        }
+        let hash = body.hash();
+        hash.encode(w)?;
+        Ok(())
+    }
+
+    ///
+    /// Reads a change written by `to_bytes`. An added subtree is rebuilt and
+    /// rehashed with `H`; whether the change fits a tree is checked by `apply`.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError>
+    where
+        V: Codec,
+    {
+        let mut r = bytes;
+        let mut body = Checksummed::new(&mut r);
+        let mut magic = [0u8; 4];
+        body.read_exact(&mut magic)?;
+        if magic != CHANGE_MAGIC {
+            return Err(FormatError::BadMagic {
+                found: magic,
+                expected: CHANGE_MAGIC,
+            });
+        }
+        let version = u16::decode(&mut body)?;
+        if version != CHANGE_FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion {
+                found: version,
+                expected: CHANGE_FORMAT_VERSION,
+            });
+        }
+        let kind = u8::decode(&mut body)?;
+        let depth = usize::decode(&mut body)?;
+        let mut path = Vec::new();
+        for _ in 0..depth {
+            path.push(usize::decode(&mut body)?);
+        }
+        let change = match kind {
+            ADDED => TreeChange::Added {
+                path,
+                subtree: read_subtree(&mut body)?,
+            },
+            REMOVED => TreeChange::Removed { path },
+            MODIFIED => TreeChange::Modified {
+                path,
+                obj: V::decode(&mut body)?,
+            },
+            _ => return Err(FormatError::Corrupt("unknown change kind")),
+        };
+
+        let found = body.hash();
+        let expected = u32::decode(&mut r)?;
+        if expected != found {
+            return Err(FormatError::ChecksumMismatch { expected, found });
+        }
+        if !r.is_empty() {
+            return Err(FormatError::Corrupt("trailing bytes after change"));
+        }
+        Ok(change)
+    }
+}
+
+fn write_subtree<V, H, W>(tree: &ObjTree<V, H>, w: &mut W) -> Result<(), FormatError>
+where
+    V: Debug + Clone + HashBytes + Codec,
+    H: TreeHasher,
+    W: Write,
+{
+    tree.size().encode(w)?;
+    for (id, obj) in tree.iter_pre_order() {
+        tree.children(id).unwrap().len().encode(w)?;
+        obj.encode(w)?;
+    }
+    Ok(())
+}
+
+fn read_subtree<V, H, R>(r: &mut R) -> Result<ObjTree<V, H>, FormatError>
+where
+    V: Debug + Clone + HashBytes + Codec,
+    H: TreeHasher,
+    R: Read,
+{
+    let count = usize::decode(r)?;
+    if count == 0 {
+        return Err(FormatError::Corrupt("added subtree has no nodes"));
+    }
+    let kids = usize::decode(r)?;
+    let mut tree = ObjTree::with_hasher(V::decode(r)?);
+    // Nodes still waiting for children, with how many they are missing
+    let mut open = vec![(tree.root(), kids)];
+    tree.batch(|tree| {
+        for _ in 1..count {
+            while let Some(&(_, 0)) = open.last() {
+                open.pop();
+            }
+            let parent = match open.last_mut() {
+                Some(&mut (id, ref mut missing)) => {
+                    *missing -= 1;
+                    id
+                }
+                None => {
+                    return Err(FormatError::Corrupt(
+                        "child counts describe fewer nodes than the count",
+                    ))
+                }
+            };
+            let kids = usize::decode(r)?;
+            let obj = V::decode(r)?;
+            let id = tree.add(parent, ObjTree::with_hasher(obj)).unwrap();
+            open.push((id, kids));
+        }
+        if open.iter().any(|&(_, missing)| missing > 0) {
+            return Err(FormatError::Corrupt(
+                "child counts describe more nodes than the count",
+            ));
+        }
+        Ok(())
+    })?;
+    Ok(tree)
+}
+
+impl<V, H> Clone for TreeChange<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    fn clone(&self) -> Self {
+        match *self {
+            TreeChange::Added {
+                ref path,
+                ref subtree,
+            } => TreeChange::Added {
+                path: path.clone(),
+                subtree: subtree.clone(),
+            },
+            TreeChange::Removed { ref path } => TreeChange::Removed { path: path.clone() },
+            TreeChange::Modified { ref path, ref obj } => TreeChange::Modified {
+                path: path.clone(),
+                obj: obj.clone(),
+            },
+        }
+    }
+}
+
+impl<V, H> Debug for TreeChange<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TreeChange::Added {
+                ref path,
+                ref subtree,
+            } => write!(
+                f,
+                "Added {:?} ({} nodes, {:?})",
+                path,
+                subtree.size(),
+                subtree.root_hash()
+            ),
+            TreeChange::Removed { ref path } => write!(f, "Removed {:?}", path),
+            TreeChange::Modified { ref path, ref obj } => {
+                write!(f, "Modified {:?} to {:?}", path, obj)
+            }
+        }
+    }
+}
+
+// Appends `index` to a copy of `path`
+fn child_path(path: &[usize], index: usize) -> Vec<usize> {
+    let mut child = path.to_vec();
+    child.push(index);
+    child
+}
+
+// Lines up two child lists, returning the (a, b) index pairs to compare further.
+// Each child of a is matched to the next unused child of b with an equal object,
+// found through a hash map on object hashes, and of those matches the longest run
+// keeping order on both sides is kept. In each gap between kept matches the
+// children are paired by position. The children left out on either side are
+// removed or added whole. For n + m children this takes O((n + m) log(n + m)).
+fn align<V, H>(
+    a: &ObjTree<V, H>,
+    a_kids: &[NodeId],
+    b: &ObjTree<V, H>,
+    b_kids: &[NodeId],
+) -> Vec<(usize, usize)>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    let (n, m) = (a_kids.len(), b_kids.len());
+    let mut positions: HashMap<&ObjHash<H::Digest>, VecDeque<usize>> = HashMap::new();
+    for (j, id) in b_kids.iter().enumerate() {
+        positions
+            .entry(b.get_obj_hash(*id).unwrap())
+            .or_insert_with(VecDeque::new)
+            .push_back(j);
+    }
+    let candidates: Vec<(usize, usize)> = a_kids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, id)| {
+            let js = positions.get_mut(a.get_obj_hash(*id).unwrap())?;
+            js.pop_front().map(|j| (i, j))
+        })
+        .collect();
+
+    // Longest increasing run of b positions, by patience sorting: tails[k] is the
+    // candidate ending the best run of length k + 1 found so far
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; candidates.len()];
+    for (c, &(_, j)) in candidates.iter().enumerate() {
+        let len = match tails.binary_search_by(|&t| candidates[t].1.cmp(&j)) {
+            Ok(len) | Err(len) => len,
+        };
+        if len > 0 {
+            prev[c] = Some(tails[len - 1]);
+        }
+        if len == tails.len() {
+            tails.push(c);
+        } else {
+            tails[len] = c;
+        }
+    }
+    let mut matches = Vec::with_capacity(tails.len());
+    let mut next = tails.last().cloned();
+    while let Some(c) = next {
+        matches.push(candidates[c]);
+        next = prev[c];
+    }
+    matches.reverse();
+
+    let mut pairs = Vec::new();
+    let (mut gap_i, mut gap_j) = (0, 0);
+    // (n, m) closes the gap before the ends
+    for &(i, j) in matches.iter().chain(Some(&(n, m))) {
+        let width = (i - gap_i).min(j - gap_j);
+        pairs.extend((0..width).map(|k| (gap_i + k, gap_j + k)));
+        if i < n {
+            pairs.push((i, j));
+        }
+        gap_i = i + 1;
+        gap_j = j + 1;
+    }
+    pairs
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-OBJTREE+++++++++++++++++++++++++++++++++++++
+impl<V, H> ObjTree<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    ///
+    /// The changes turning this tree into `other`, to be handed to `apply`.
+    ///
+    /// Subtrees with equal tree hashes are taken as equal and skipped, so only the
+    /// changed parts are descended into. Children matching at either end of a child
+    /// list are skipped as well, and the rest lined up by their object hashes before
+    /// being compared in pairs; lining up k children takes O(k log k).
+    ///
+    pub fn diff(&self, other: &Self) -> Vec<TreeChange<V, H>> {
+        let mut changes = Vec::new();
+        let mut stack = vec![(self.root(), other.root(), Vec::new())];
+        while let Some((a, b, path)) = stack.pop() {
+            if self.get_tree_hash(a) == other.get_tree_hash(b) {
+                continue;
+            }
+            if self.get_obj_hash(a) != other.get_obj_hash(b) {
+                changes.push(TreeChange::Modified {
+                    path: path.clone(),
+                    obj: other.get(b).unwrap().clone(),
+                });
+            }
+            let a_kids = self.children(a).unwrap();
+            let b_kids = other.children(b).unwrap();
+            let same = |x: &NodeId, y: &NodeId| self.get_tree_hash(*x) == other.get_tree_hash(*y);
+            let front = a_kids
+                .iter()
+                .zip(b_kids.iter())
+                .take_while(|&(x, y)| same(x, y))
+                .count();
+            let back = a_kids[front..]
+                .iter()
+                .rev()
+                .zip(b_kids[front..].iter().rev())
+                .take_while(|&(x, y)| same(x, y))
+                .count();
+            let a_mid = &a_kids[front..a_kids.len() - back];
+            let b_mid = &b_kids[front..b_kids.len() - back];
+            let pairs = align(self, a_mid, other, b_mid);
+
+            // Walks both lists in step, `index` being the position in this tree once
+            // the changes so far are applied. Changes inside the paired children
+            // come later, when this level already has its final order.
+            let (mut ai, mut bi, mut index) = (0, 0, front);
+            let ends = pairs.iter().cloned().map(Some).chain(Some(None));
+            for pair in ends {
+                let (pa, pb) = pair.unwrap_or((a_mid.len(), b_mid.len()));
+                for _ in ai..pa {
+                    changes.push(TreeChange::Removed {
+                        path: child_path(&path, index),
+                    });
+                }
+                for id in &b_mid[bi..pb] {
+                    changes.push(TreeChange::Added {
+                        path: child_path(&path, index),
+                        subtree: other.subtree(*id).unwrap(),
+                    });
+                    index += 1;
+                }
+                ai = pa + 1;
+                bi = pb + 1;
+                index += 1;
+            }
+            for &(pa, pb) in pairs.iter().rev() {
+                stack.push((a_mid[pa], b_mid[pb], child_path(&path, front + pb)));
+            }
+        }
+        changes
+    }
+
+    ///
+    /// Applies `changes` in order, as produced by `diff`.
+    ///
+    /// The first change whose path does not fit the tree is handed back; the ones
+    /// before it stay applied. The root can be modified but not added or removed.
+    /// The changes run as one `batch`, so each touched node is rehashed once.
+    ///
+    pub fn apply<I>(&mut self, changes: I) -> Result<(), TreeChange<V, H>>
+    where
+        I: IntoIterator<Item = TreeChange<V, H>>,
+    {
+        self.batch(|tree| {
+            for change in changes {
+                tree.apply_one(change)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn apply_one(&mut self, change: TreeChange<V, H>) -> Result<(), TreeChange<V, H>> {
+        let applied = match change {
+            TreeChange::Modified { ref path, ref obj } => match self.node_at(path) {
+                Some(id) => self.replace(id, obj.clone()).is_some(),
+                None => false,
+            },
+            TreeChange::Removed { ref path } => match self.node_at(path) {
+                Some(id) => self.split(id).is_some(),
+                None => false,
+            },
+            TreeChange::Added { path, subtree } => {
+                let parent = path.split_last().and_then(|(_, up)| self.node_at(up));
+                return match (parent, path.last()) {
+                    (Some(parent), Some(&index)) => {
+                        match self.insert_child(parent, index, subtree) {
+                            Ok(_) => Ok(()),
+                            Err(subtree) => Err(TreeChange::Added { path, subtree }),
+                        }
+                    }
+                    _ => Err(TreeChange::Added { path, subtree }),
+                };
+            }
+        };
+        if applied {
+            Ok(())
+        } else {
+            Err(change)
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::merkle::WideHasher;
+
+// Builds a tree from (parent path, obj) pairs, each added as a last child
+#[cfg(test)]
+fn build<H: TreeHasher>(root: i32, adds: &[(&[usize], i32)]) -> ObjTree<i32, H> {
+    let mut tree = ObjTree::with_hasher(root);
+    for &(path, obj) in adds {
+        let parent = tree.node_at(path).unwrap();
+        tree.add(parent, ObjTree::with_hasher(obj)).unwrap();
+    }
+    tree
+}
+
+#[cfg(test)]
+fn objs<H: TreeHasher>(tree: &ObjTree<i32, H>) -> Vec<i32> {
+    tree.iter_pre_order().map(|(_, v)| *v).collect()
+}
+
+// Diffs a against b, patches a copy of a and checks it came out as b
+#[cfg(test)]
+fn sync<H: TreeHasher>(a: &ObjTree<i32, H>, b: &ObjTree<i32, H>) -> Vec<TreeChange<i32, H>> {
+    let changes = a.diff(b);
+    let mut patched = a.clone();
+    patched.apply(changes.clone()).unwrap();
+    assert_eq!(patched.root_hash(), b.root_hash());
+    assert_eq!(objs(&patched), objs(b));
+    changes
+}
+
+#[cfg(test)]
+fn paths<H: TreeHasher>(changes: &[TreeChange<i32, H>]) -> Vec<Vec<usize>> {
+    changes.iter().map(|c| c.path().to_vec()).collect()
+}
+
+// 1 has children 2, 3 and 4; 2 has children 5 and 6; 4 has a child 7
+#[cfg(test)]
+const SAMPLE: &[(&[usize], i32)] = &[
+    (&[], 2),
+    (&[], 3),
+    (&[], 4),
+    (&[0], 5),
+    (&[0], 6),
+    (&[2], 7),
+];
+
+#[test]
+fn equal_trees_have_no_diff() {
+    let a: ObjTree<i32> = build(1, SAMPLE);
+    let b: ObjTree<i32> = build(1, SAMPLE);
+    assert!(a.diff(&b).is_empty());
+    assert!(a.diff(&a.clone()).is_empty());
+}
+
+#[test]
+fn diff_descends_only_into_changed_subtrees() {
+    let a: ObjTree<i32> = build(1, SAMPLE);
+
+    let mut b = a.clone();
+    let six = b.node_at(&[0, 1]).unwrap();
+    b.replace(six, 60);
+    let changes = sync(&a, &b);
+    assert_eq!(paths(&changes), vec![vec![0, 1]]);
+    match changes[0] {
+        TreeChange::Modified { obj: 60, .. } => {}
+        ref other => panic!("expected a modification, got {:?}", other),
+    }
+
+    // a child put in front is one addition, not a shift of every sibling
+    let mut c = a.clone();
+    let root = c.root();
+    c.insert_child(root, 0, ObjTree::new(9)).unwrap();
+    let changes = sync(&a, &c);
+    assert_eq!(paths(&changes), vec![vec![0]]);
+    match changes[0] {
+        TreeChange::Added { ref subtree, .. } => assert_eq!(objs(subtree), vec![9]),
+        ref other => panic!("expected an addition, got {:?}", other),
+    }
+
+    let mut d = a.clone();
+    let two = d.node_at(&[0]).unwrap();
+    d.split(two);
+    let changes = sync(&a, &d);
+    assert_eq!(paths(&changes), vec![vec![0]]);
+    match changes[0] {
+        TreeChange::Removed { .. } => {}
+        ref other => panic!("expected a removal, got {:?}", other),
+    }
+}
+
+#[test]
+fn diff_mixed_changes() {
+    let a: ObjTree<i32> = build(1, SAMPLE);
+    let b: ObjTree<i32> = build(
+        10,
+        &[
+            (&[], 2),
+            (&[], 8),
+            (&[0], 5),
+            (&[0], 11),
+            (&[0], 12),
+            (&[1], 13),
+        ],
+    );
+    let changes = sync(&a, &b);
+    assert_eq!(format!("{:?}", changes[0]), "Modified [] to 10");
+    assert!(changes.len() < b.size() + a.size());
+
+    // and back again
+    sync(&b, &a);
+
+    // trees growing from a single root
+    let leaf: ObjTree<i32> = ObjTree::new(1);
+    let changes = sync(&leaf, &a);
+    assert_eq!(paths(&changes), vec![vec![0], vec![1], vec![2]]);
+    assert_eq!(paths(&sync(&a, &leaf)), vec![vec![0], vec![0], vec![0]]);
+}
+
+#[test]
+fn diff_with_wide_hasher() {
+    let a: ObjTree<i32, WideHasher> = build(1, SAMPLE);
+    let mut b = a.clone();
+    let seven = b.node_at(&[2, 0]).unwrap();
+    b.add(seven, ObjTree::with_hasher(70)).unwrap();
+    let three = b.node_at(&[1]).unwrap();
+    b.split(three);
+    let changes = sync(&a, &b);
+    assert_eq!(paths(&changes), vec![vec![1], vec![1, 0, 0]]);
+}
+
+#[test]
+fn diff_of_generated_trees() {
+    // small linear congruential generator, so runs are repeatable
+    let mut seed: u64 = 7;
+    let mut next = move |bound: usize| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize % bound
+    };
+    let mut trees: Vec<ObjTree<i32>> = Vec::new();
+    for _ in 0..6 {
+        let mut tree = ObjTree::new(0);
+        let mut ids = vec![tree.root()];
+        for _ in 0..40 {
+            let parent = ids[next(ids.len())];
+            let id = tree.add(parent, ObjTree::new(next(5) as i32)).unwrap();
+            ids.push(id);
+        }
+        trees.push(tree);
+    }
+    for a in &trees {
+        for b in &trees {
+            sync(a, b);
+        }
+    }
+}
+
+#[test]
+fn diff_of_wide_child_lists() {
+    let mut a: ObjTree<i32> = ObjTree::new(-1);
+    let root = a.root();
+    a.batch(|tree| {
+        for v in 0..50_000 {
+            tree.add(root, ObjTree::new(v)).unwrap();
+        }
+    });
+    let mut b = a.clone();
+    let root = b.root();
+    b.batch(|tree| {
+        let gone = tree.node_at(&[10_000]).unwrap();
+        tree.split(gone);
+        let changed = tree.node_at(&[30_000]).unwrap();
+        tree.replace(changed, -2);
+        tree.insert_child(root, 40_000, ObjTree::new(-3)).unwrap();
+    });
+    let changes = sync(&a, &b);
+    assert_eq!(
+        paths(&changes),
+        vec![vec![10_000], vec![40_000], vec![30_000]]
+    );
+    assert_eq!(format!("{:?}", changes[2]), "Modified [30000] to -2");
+}
+
+#[test]
+fn apply_rejects_bad_paths() {
+    let a: ObjTree<i32> = build(1, SAMPLE);
+    let mut tree = a.clone();
+    let changes = vec![
+        TreeChange::Modified {
+            path: vec![0],
+            obj: 20,
+        },
+        TreeChange::Removed { path: vec![5] },
+        TreeChange::Modified {
+            path: vec![1],
+            obj: 30,
+        },
+    ];
+    match tree.apply(changes) {
+        Err(TreeChange::Removed { ref path }) if *path == vec![5] => {}
+        other => panic!("expected the removal back, got {:?}", other),
+    }
+    assert_eq!(objs(&tree), vec![1, 20, 5, 6, 3, 4, 7]);
+
+    let mut tree = a.clone();
+    assert!(tree
+        .apply(vec![TreeChange::Removed { path: vec![] }])
+        .is_err());
+    let added = TreeChange::Added {
+        path: vec![],
+        subtree: ObjTree::new(9),
+    };
+    assert!(tree.apply(vec![added]).is_err());
+    let added = TreeChange::Added {
+        path: vec![0, 3],
+        subtree: ObjTree::new(9),
+    };
+    match tree.apply(vec![added]) {
+        Err(TreeChange::Added { ref subtree, .. }) => assert_eq!(objs(subtree), vec![9]),
+        other => panic!("expected the addition back, got {:?}", other),
+    }
+    assert_eq!(tree.root_hash(), a.root_hash());
+}
+
+#[test]
+fn change_bytes_round_trip() {
+    let a: ObjTree<i32> = build(1, SAMPLE);
+    let b: ObjTree<i32> = build(
+        10,
+        &[
+            (&[], 2),
+            (&[], 8),
+            (&[0], 5),
+            (&[0], 11),
+            (&[0], 12),
+            (&[1], 13),
+        ],
+    );
+    let changes = a.diff(&b);
+    let mut shipped = Vec::new();
+    for change in &changes {
+        let bytes = change.to_bytes();
+        assert_eq!(&bytes[..4], b"GTCH");
+        let back: TreeChange<i32> = TreeChange::from_bytes(&bytes).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", change));
+        shipped.push(back);
+    }
+    let mut patched = a.clone();
+    patched.apply(shipped).unwrap();
+    assert_eq!(patched.root_hash(), b.root_hash());
+
+    // a deeper subtree keeps its shape
+    let added = TreeChange::Added {
+        path: vec![0, 2],
+        subtree: a.clone(),
+    };
+    match TreeChange::<i32>::from_bytes(&added.to_bytes()).unwrap() {
+        TreeChange::Added { path, subtree } => {
+            assert_eq!(path, vec![0, 2]);
+            assert_eq!(subtree.root_hash(), a.root_hash());
+            assert_eq!(objs(&subtree), objs(&a));
+        }
+        other => panic!("expected an addition, got {:?}", other),
+    }
+
+    let wide: TreeChange<i32, WideHasher> = TreeChange::Added {
+        path: vec![1],
+        subtree: build(1, SAMPLE),
+    };
+    let back = TreeChange::<i32, WideHasher>::from_bytes(&wide.to_bytes()).unwrap();
+    assert_eq!(format!("{:?}", back), format!("{:?}", wide));
+}
+
+#[test]
+fn change_bytes_errors() {
+    let added: TreeChange<i32> = TreeChange::Added {
+        path: vec![1],
+        subtree: build(1, SAMPLE),
+    };
+    let bytes = added.to_bytes();
+
+    match TreeChange::<i32>::from_bytes(&bytes[..bytes.len() - 1]) {
+        Err(FormatError::Truncated) => {}
+        other => panic!("expected Truncated, got {:?}", other),
+    }
+    let mut longer = bytes.clone();
+    longer.push(0);
+    match TreeChange::<i32>::from_bytes(&longer) {
+        Err(FormatError::Corrupt(_)) => {}
+        other => panic!("expected Corrupt, got {:?}", other),
+    }
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    match TreeChange::<i32>::from_bytes(&magic) {
+        Err(FormatError::BadMagic { expected, .. }) => assert_eq!(&expected, b"GTCH"),
+        other => panic!("expected BadMagic, got {:?}", other),
+    }
+    let mut version = bytes.clone();
+    version[4] = 1;
+    match TreeChange::<i32>::from_bytes(&version) {
+        Err(FormatError::UnsupportedVersion {
+            found: 1,
+            expected: 2,
+        }) => {}
+        other => panic!("expected UnsupportedVersion, got {:?}", other),
+    }
+    let mut kind = bytes.clone();
+    kind[6] = 7;
+    match TreeChange::<i32>::from_bytes(&kind) {
+        Err(FormatError::Corrupt(_)) => {}
+        other => panic!("expected Corrupt, got {:?}", other),
+    }
+
+    // magic, version, kind and a one level path come before the node count,
+    // then the root's child count
+    let counts = 4 + 2 + 1 + 16;
+    let mut fewer = bytes.clone();
+    fewer[counts] += 1;
+    match TreeChange::<i32>::from_bytes(&fewer) {
+        Err(FormatError::Corrupt(_)) => {}
+        other => panic!("expected Corrupt, got {:?}", other),
+    }
+    let mut more = bytes.clone();
+    more[counts + 8] += 1;
+    match TreeChange::<i32>::from_bytes(&more) {
+        Err(FormatError::Corrupt(_)) => {}
+        other => panic!("expected Corrupt, got {:?}", other),
+    }
+
+    // a flipped bit in an object still decodes, the checksum catches it
+    let mut flipped = bytes.clone();
+    let last = flipped.len() - 5;
+    flipped[last] ^= 0x40;
+    match TreeChange::<i32>::from_bytes(&flipped) {
+        Err(FormatError::ChecksumMismatch { .. }) => {}
+        other => panic!("expected ChecksumMismatch, got {:?}", other),
+    }
+}
@@ -0,0 +1,982 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// A hierarchical tree of generic objects, any number of children per node
+//
+//
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+
+use super::{HashBytes, SipTreeHasher, TreeHasher};
+
+///
+/// Digest of a single object. D is the TreeHasher's digest, u64 by default.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjHash<D = u64>(pub D);
+
+///
+/// Merkle digest of a whole subtree.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjTreeHash<D = u64>(pub D);
+
+///
+/// Handle to a node of an ObjTree.
+///
+/// Ids stay valid until their node is split off. A later `add` may reuse the
+/// slot, but each id also carries the slot's generation, which moves on when the
+/// node leaves, so an old id finds nothing there instead of the new node.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u64,
+}
+
+impl NodeId {
+    fn first(index: usize) -> Self {
+        NodeId {
+            index,
+            generation: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ObjNode<V, D> {
+    obj: V,
+    obj_hash: ObjHash<D>,
+    tree_hash: ObjTreeHash<D>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    // Set inside a batch on nodes whose tree hash is waiting to be refreshed
+    dirty: bool,
+}
+
+// An arena slot, empty once its node is split off
+#[derive(Clone)]
+struct Slot<V, D> {
+    generation: u64,
+    node: Option<ObjNode<V, D>>,
+}
+
+///
+/// An ObjTree holds objects of type V in nodes with any number of ordered children.
+///
+/// The nodes live in one arena and refer to each other by NodeId, each keeping its
+/// parent and its children, so the tree can be walked both ways. There is always a
+/// root. Whole subtrees are moved in with `add` or `insert_child` and taken out
+/// with `split`.
+///
+/// Every node keeps the hash of its object and a Merkle tree hash covering that
+/// and the tree hashes of its children, so the root hash stands for the whole tree.
+/// Edits refresh only the tree hashes on the way from the change up to the root,
+/// and `batch` puts that off so bulk edits refresh each node once. Objects are
+/// hashed through `HashBytes`, and H picks the hash function, see `TreeHasher`.
+///
+pub struct ObjTree<V, H = SipTreeHasher>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    nodes: Vec<Slot<V, H::Digest>>,
+    free: Vec<usize>,
+    root: NodeId,
+    deferred: bool,
+    hasher: PhantomData<H>,
+}
+
+impl<V, H> Clone for ObjTree<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    fn clone(&self) -> Self {
+        let mut tree = ObjTree {
+            nodes: self.nodes.clone(),
+            free: self.free.clone(),
+            root: self.root,
+            deferred: false,
+            hasher: PhantomData,
+        };
+        tree.flush();
+        tree
+    }
+}
+
+///
+/// Pre-order iteration of an ObjTree.
+///
+pub struct ObjTreeIter<'a, V: 'a, H: 'a = SipTreeHasher>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    tree: &'a ObjTree<V, H>,
+    stack: Vec<NodeId>,
+}
+
+impl<'a, V, H> Iterator for ObjTreeIter<'a, V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    type Item = (NodeId, &'a V);
+
+    fn next(&mut self) -> Option<(NodeId, &'a V)> {
+        let id = self.stack.pop()?;
+        let node = self.tree.node(id)?;
+        self.stack.extend(node.children.iter().rev());
+        Some((id, &node.obj))
+    }
+}
+
+///
+/// The ancestors of a node, from its parent up to the root.
+///
+pub struct ObjTreeAncestors<'a, V: 'a, H: 'a = SipTreeHasher>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    tree: &'a ObjTree<V, H>,
+    next: Option<NodeId>,
+}
+
+impl<'a, V, H> Iterator for ObjTreeAncestors<'a, V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.tree.parent(id);
+        Some(id)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-OBJTREE+++++++++++++++++++++++++++++++++++++
+impl<V> ObjTree<V>
+where
+    V: Debug + Clone + HashBytes,
+{
+    ///
+    /// Create a new ObjTree holding just `obj` at the root, hashed with the
+    /// default SipTreeHasher.
+    ///
+    pub fn new(obj: V) -> Self {
+        ObjTree::with_hasher(obj)
+    }
+}
+
+impl<V, H> ObjTree<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    ///
+    /// Create a new ObjTree holding just `obj` at the root, hashed with H.
+    ///
+    pub fn with_hasher(obj: V) -> Self {
+        let obj_hash = H::hash_obj(&obj);
+        let tree_hash = H::hash_node(&obj_hash, &[]);
+        ObjTree {
+            nodes: vec![Slot {
+                generation: 0,
+                node: Some(ObjNode {
+                    obj,
+                    obj_hash: ObjHash(obj_hash),
+                    tree_hash: ObjTreeHash(tree_hash),
+                    parent: None,
+                    children: Vec::new(),
+                    dirty: false,
+                }),
+            }],
+            free: Vec::new(),
+            root: NodeId::first(0),
+            deferred: false,
+            hasher: PhantomData,
+        }
+    }
+
+    fn node(&self, id: NodeId) -> Option<&ObjNode<V, H::Digest>> {
+        match self.nodes.get(id.index) {
+            Some(slot) if slot.generation == id.generation => slot.node.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut ObjNode<V, H::Digest>> {
+        match self.nodes.get_mut(id.index) {
+            Some(slot) if slot.generation == id.generation => slot.node.as_mut(),
+            _ => None,
+        }
+    }
+
+    // Picks a slot for a node about to be moved in
+    fn reserve(&mut self) -> NodeId {
+        match self.free.pop() {
+            Some(index) => NodeId {
+                index,
+                generation: self.nodes[index].generation,
+            },
+            None => {
+                self.nodes.push(Slot {
+                    generation: 0,
+                    node: None,
+                });
+                NodeId::first(self.nodes.len() - 1)
+            }
+        }
+    }
+
+    // Empties the slot of `id`, moving its generation on so the id goes stale
+    fn release(&mut self, id: NodeId) -> Option<ObjNode<V, H::Digest>> {
+        self.node(id)?;
+        let slot = &mut self.nodes[id.index];
+        slot.generation += 1;
+        self.free.push(id.index);
+        slot.node.take()
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    ///
+    /// Returns the object held by `id`.
+    ///
+    pub fn get(&self, id: NodeId) -> Option<&V> {
+        self.node(id).map(|n| &n.obj)
+    }
+
+    ///
+    /// Follows `path`, a child index per level, down from the root.
+    ///
+    pub fn node_at(&self, path: &[usize]) -> Option<NodeId> {
+        let mut id = self.root;
+        for index in path {
+            id = *self.node(id)?.children.get(*index)?;
+        }
+        Some(id)
+    }
+
+    ///
+    /// The child indices leading from the root to `id`, empty for the root.
+    ///
+    pub fn path_of(&self, id: NodeId) -> Option<Vec<usize>> {
+        let mut node = self.node(id)?;
+        let mut path = Vec::new();
+        let mut id = id;
+        while let Some(parent) = node.parent {
+            node = self.node(parent)?;
+            path.push(node.children.iter().position(|c| *c == id)?);
+            id = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    ///
+    /// Number of nodes in the tree.
+    ///
+    pub fn size(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    ///
+    /// Add a sub tree as the last child of `parent`. The sub tree is consumed and
+    /// the id of its root in this tree returned.
+    ///
+    /// The sub tree is handed back if `parent` is not in the tree.
+    ///
+    pub fn add(&mut self, parent: NodeId, subtree: Self) -> Result<NodeId, Self> {
+        match self.node(parent) {
+            Some(n) => {
+                let index = n.children.len();
+                self.insert_child(parent, index, subtree)
+            }
+            None => Err(subtree),
+        }
+    }
+
+    ///
+    /// Add a sub tree as child number `index` of `parent`, shifting later children
+    /// to the right.
+    ///
+    /// The sub tree is handed back if `parent` is not in the tree or has fewer than
+    /// `index` children.
+    ///
+    pub fn insert_child(
+        &mut self,
+        parent: NodeId,
+        index: usize,
+        subtree: Self,
+    ) -> Result<NodeId, Self> {
+        match self.node(parent) {
+            Some(n) if index <= n.children.len() => {}
+            _ => return Err(subtree),
+        }
+        let mut subtree = subtree;
+        subtree.flush();
+        // Every node gets a slot here first, so the links can be renumbered
+        let ids: Vec<Option<NodeId>> = subtree
+            .nodes
+            .iter()
+            .map(|slot| slot.node.as_ref().map(|_| self.reserve()))
+            .collect();
+        let renumber = |id: NodeId| ids[id.index].unwrap();
+        let top = renumber(subtree.root);
+        for (old, slot) in subtree.nodes.into_iter().enumerate() {
+            if let Some(mut node) = slot.node {
+                node.parent = node.parent.map(renumber);
+                node.children = node.children.iter().cloned().map(renumber).collect();
+                self.nodes[ids[old].unwrap().index].node = Some(node);
+            }
+        }
+        if let Some(n) = self.node_mut(top) {
+            n.parent = Some(parent);
+        }
+        if let Some(n) = self.node_mut(parent) {
+            n.children.insert(index, top);
+        }
+        self.rehash_up(parent);
+        Ok(top)
+    }
+
+    ///
+    /// Remove the subtree rooted at `id` from the tree and return ownership of it.
+    ///
+    /// The root cannot be split off, as a tree is never empty.
+    ///
+    pub fn split(&mut self, id: NodeId) -> Option<Self> {
+        let parent = self.node(id)?.parent?;
+        if let Some(n) = self.node_mut(parent) {
+            n.children.retain(|c| *c != id);
+        }
+        self.rehash_up(parent);
+
+        // Pre-order puts the subtree's root first
+        let order: Vec<NodeId> = self.iter_from(id).map(|(id, _)| id).collect();
+        let new_ids: HashMap<NodeId, NodeId> = order
+            .iter()
+            .enumerate()
+            .map(|(i, old)| (*old, NodeId::first(i)))
+            .collect();
+        let mut nodes = Vec::with_capacity(order.len());
+        for old in order {
+            let mut node = self.release(old).unwrap();
+            node.parent = node.parent.and_then(|p| new_ids.get(&p).cloned());
+            node.children = node.children.iter().map(|c| new_ids[c]).collect();
+            nodes.push(Slot {
+                generation: 0,
+                node: Some(node),
+            });
+        }
+        let mut tree = ObjTree {
+            nodes,
+            free: Vec::new(),
+            root: NodeId::first(0),
+            deferred: false,
+            hasher: PhantomData,
+        };
+        tree.flush();
+        Some(tree)
+    }
+
+    ///
+    /// Copy the subtree rooted at `id` into a tree of its own, leaving this one as
+    /// it is.
+    ///
+    pub fn subtree(&self, id: NodeId) -> Option<Self> {
+        self.node(id)?;
+        let order: Vec<NodeId> = self.iter_from(id).map(|(id, _)| id).collect();
+        let new_ids: HashMap<NodeId, NodeId> = order
+            .iter()
+            .enumerate()
+            .map(|(i, old)| (*old, NodeId::first(i)))
+            .collect();
+        let nodes = order
+            .iter()
+            .map(|old| {
+                let mut node = self.node(*old).cloned().unwrap();
+                node.parent = node.parent.and_then(|p| new_ids.get(&p).cloned());
+                node.children = node.children.iter().map(|c| new_ids[c]).collect();
+                Slot {
+                    generation: 0,
+                    node: Some(node),
+                }
+            })
+            .collect();
+        let mut tree = ObjTree {
+            nodes,
+            free: Vec::new(),
+            root: NodeId::first(0),
+            deferred: false,
+            hasher: PhantomData,
+        };
+        tree.flush();
+        Some(tree)
+    }
+
+    ///
+    /// Put `obj` in place of the object held by `id` and return the old one.
+    ///
+    pub fn replace(&mut self, id: NodeId, obj: V) -> Option<V> {
+        let obj_hash = ObjHash(H::hash_obj(&obj));
+        let old = {
+            let n = self.node_mut(id)?;
+            n.obj_hash = obj_hash;
+            mem::replace(&mut n.obj, obj)
+        };
+        self.rehash_up(id);
+        Some(old)
+    }
+
+    ///
+    /// Return the children of `id` in order.
+    ///
+    pub fn children(&self, id: NodeId) -> Option<&[NodeId]> {
+        self.node(id).map(|n| &n.children[..])
+    }
+
+    ///
+    /// Return the parent of `id`, None for the root.
+    ///
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id)?.parent
+    }
+
+    ///
+    /// Return the other children of the parent of `id`, in order. The root has none.
+    ///
+    pub fn siblings(&self, id: NodeId) -> Option<Vec<NodeId>> {
+        let node = self.node(id)?;
+        Some(match node.parent.and_then(|p| self.node(p)) {
+            Some(p) => p.children.iter().cloned().filter(|c| *c != id).collect(),
+            None => Vec::new(),
+        })
+    }
+
+    ///
+    /// Return the child of the same parent right after `id`, if any.
+    ///
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        let siblings = self.children(self.parent(id)?)?;
+        let at = siblings.iter().position(|c| *c == id)?;
+        siblings.get(at + 1).cloned()
+    }
+
+    ///
+    /// Return the child of the same parent right before `id`, if any.
+    ///
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        let siblings = self.children(self.parent(id)?)?;
+        let at = siblings.iter().position(|c| *c == id)?;
+        at.checked_sub(1).map(|before| siblings[before])
+    }
+
+    ///
+    /// Walk up from `id` through its parent to the root. Nothing for the root, or
+    /// for an id not in the tree.
+    ///
+    pub fn ancestors(&self, id: NodeId) -> ObjTreeAncestors<'_, V, H> {
+        ObjTreeAncestors {
+            tree: self,
+            next: self.parent(id),
+        }
+    }
+
+    ///
+    /// Return the degree of `id` - number of children
+    ///
+    pub fn degree(&self, id: NodeId) -> Option<usize> {
+        self.node(id).map(|n| n.children.len())
+    }
+
+    ///
+    /// Return the depth of `id`, counted in nodes so the root is at depth 1.
+    ///
+    pub fn depth(&self, id: NodeId) -> Option<usize> {
+        self.node(id)?;
+        Some(self.ancestors(id).count() + 1)
+    }
+
+    ///
+    /// Return the height of the subtree under `id`, the number of nodes on its
+    /// longest downward path. A leaf has height 1.
+    ///
+    pub fn height(&self, id: NodeId) -> Option<usize> {
+        self.node(id)?;
+        let mut stack = vec![(id, 1)];
+        let mut height = 0;
+        while let Some((id, depth)) = stack.pop() {
+            height = height.max(depth);
+            if let Some(n) = self.node(id) {
+                stack.extend(n.children.iter().map(|c| (*c, depth + 1)));
+            }
+        }
+        Some(height)
+    }
+
+    pub fn get_obj_hash(&self, id: NodeId) -> Option<&ObjHash<H::Digest>> {
+        self.node(id).map(|n| &n.obj_hash)
+    }
+
+    pub fn get_tree_hash(&self, id: NodeId) -> Option<&ObjTreeHash<H::Digest>> {
+        self.node(id).map(|n| &n.tree_hash)
+    }
+
+    ///
+    /// The tree hash of the root, covering every object and the shape of the tree.
+    ///
+    pub fn root_hash(&self) -> &ObjTreeHash<H::Digest> {
+        &self.node(self.root).unwrap().tree_hash
+    }
+
+    ///
+    /// Walks the whole tree in pre-order, parents before their children.
+    ///
+    pub fn iter_pre_order(&self) -> ObjTreeIter<'_, V, H> {
+        self.iter_from(self.root)
+    }
+
+    fn iter_from(&self, id: NodeId) -> ObjTreeIter<'_, V, H> {
+        ObjTreeIter {
+            tree: self,
+            stack: vec![id],
+        }
+    }
+
+    fn calculate_tree_hash(&self, n: &ObjNode<V, H::Digest>) -> ObjTreeHash<H::Digest> {
+        let children: Vec<H::Digest> = n
+            .children
+            .iter()
+            .filter_map(|c| self.node(*c))
+            .map(|c| c.tree_hash.0.clone())
+            .collect();
+        ObjTreeHash(H::hash_node(&n.obj_hash.0, &children))
+    }
+
+    ///
+    /// Runs `edits` with rehashing held back, then refreshes every tree hash they
+    /// touched once, children before parents, and returns what `edits` returned.
+    ///
+    /// Outside a batch each edit rehashes every ancestor of the change over all of
+    /// its children, so building a chain of n nodes, or a node with n children, one
+    /// `add` at a time costs O(n²). Inside a batch the same build costs O(n). Tree
+    /// hashes read inside `edits` may be out of date.
+    ///
+    pub fn batch<F, R>(&mut self, edits: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let outer = self.deferred;
+        self.deferred = true;
+        let batch = Batch { tree: self, outer };
+        edits(&mut *batch.tree)
+    }
+
+    // Rehashes the nodes marked inside a batch, children before their parents.
+    // Marks always reach up to the root, so the walk only enters marked nodes.
+    fn flush(&mut self) {
+        let mut stack = vec![(self.root, false)];
+        while let Some((id, children_done)) = stack.pop() {
+            let n = match self.node(id) {
+                Some(n) if n.dirty => n,
+                _ => continue,
+            };
+            if !children_done {
+                stack.push((id, true));
+                stack.extend(n.children.iter().map(|c| (*c, false)));
+                continue;
+            }
+            let tree_hash = self.calculate_tree_hash(n);
+            if let Some(n) = self.node_mut(id) {
+                n.tree_hash = tree_hash;
+                n.dirty = false;
+            }
+        }
+    }
+
+    // Refreshes the tree hashes of `id` and all of its ancestors, the only ones an
+    // edit below `id` can change. Inside a batch they are marked instead, up to the
+    // first one already marked, since everything above that is marked too.
+    fn rehash_up(&mut self, id: NodeId) {
+        let mut next = Some(id);
+        if self.deferred {
+            while let Some(n) = next.and_then(|id| self.node_mut(id)) {
+                if n.dirty {
+                    return;
+                }
+                n.dirty = true;
+                next = n.parent;
+            }
+            return;
+        }
+        while let Some(id) = next {
+            let (tree_hash, parent) = match self.node(id) {
+                Some(n) => (self.calculate_tree_hash(n), n.parent),
+                None => return,
+            };
+            if let Some(n) = self.node_mut(id) {
+                n.tree_hash = tree_hash;
+            }
+            next = parent;
+        }
+    }
+}
+
+// Ends a batch, catching up on the hashing even if the edits panicked
+struct Batch<'a, V: 'a, H: 'a>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    tree: &'a mut ObjTree<V, H>,
+    outer: bool,
+}
+
+impl<'a, V, H> Drop for Batch<'a, V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    fn drop(&mut self) {
+        if !self.outer {
+            self.tree.deferred = false;
+            self.tree.flush();
+        }
+    }
+}
+
+///
+/// Hashes the root hash, so equal trees hash alike whatever their ids.
+///
+impl<V, H> Hash for ObjTree<V, H>
+where
+    V: HashBytes + Clone + Debug,
+    H: TreeHasher,
+{
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.root_hash().hash(state);
+    }
+}
+
+///
+/// One line per node, indented by depth.
+///
+impl<V, H> Debug for ObjTree<V, H>
+where
+    V: HashBytes + Clone + Debug,
+    H: TreeHasher,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut stack = vec![(self.root, 0)];
+        while let Some((id, indent)) = stack.pop() {
+            let n = match self.node(id) {
+                Some(n) => n,
+                None => continue,
+            };
+            writeln!(
+                f,
+                "{:width$}obj: {:?} obj_hash: {:?} tree_hash: {:?}",
+                "",
+                n.obj,
+                n.obj_hash,
+                n.tree_hash,
+                width = indent * 2
+            )?;
+            stack.extend(n.children.iter().rev().map(|c| (*c, indent + 1)));
+        }
+        Ok(())
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use std::collections::hash_map::DefaultHasher;
+
+// An id no sample tree has a node for
+#[cfg(test)]
+const MISSING: NodeId = NodeId {
+    index: 9,
+    generation: 0,
+};
+
+// 20 has children 30 and 50, and 30 has a child 40
+#[cfg(test)]
+fn obj_sample() -> (ObjTree<i32>, Vec<NodeId>) {
+    let mut tree = ObjTree::new(20);
+    let root = tree.root();
+    let thirty = tree.add(root, ObjTree::new(30)).unwrap();
+    let forty = tree.add(thirty, ObjTree::new(40)).unwrap();
+    let fifty = tree.add(root, ObjTree::new(50)).unwrap();
+    (tree, vec![root, thirty, forty, fifty])
+}
+
+#[cfg(test)]
+fn objs(tree: &ObjTree<i32>) -> Vec<i32> {
+    tree.iter_pre_order().map(|(_, v)| *v).collect()
+}
+
+#[test]
+fn add_tree() {
+    let mut tree1 = ObjTree::new(20);
+    let mut tree2 = ObjTree::new(30);
+    let tree3 = ObjTree::new(40);
+    let root2 = tree2.root();
+    tree2.add(root2, tree3).unwrap();
+    let root1 = tree1.root();
+    let id = tree1.add(root1, tree2).unwrap();
+
+    assert_eq!(tree1.size(), 3);
+    assert_eq!(tree1.get(id), Some(&30));
+    assert_eq!(objs(&tree1), vec![20, 30, 40]);
+    assert!(format!("{:?}", tree1).starts_with("obj: 20 obj_hash: ObjHash("));
+    assert!(format!("{:?}", tree1).contains("\n    obj: 40 "));
+}
+
+#[test]
+fn insert_child_at_index() {
+    let (mut tree, ids) = obj_sample();
+    let root = ids[0];
+    let ten = tree.insert_child(root, 0, ObjTree::new(10)).unwrap();
+    tree.insert_child(root, 2, ObjTree::new(35)).unwrap();
+    assert_eq!(tree.children(root).unwrap()[0], ten);
+    assert_eq!(objs(&tree), vec![20, 10, 30, 40, 35, 50]);
+
+    // past the end, or under a node that is not there, hands the tree back
+    let back = tree.insert_child(root, 9, ObjTree::new(99)).unwrap_err();
+    assert_eq!(back.get(back.root()), Some(&99));
+    assert!(tree.add(MISSING, ObjTree::new(99)).is_err());
+    assert_eq!(tree.size(), 6);
+}
+
+#[test]
+fn split_returns_subtree() {
+    let (mut tree, ids) = obj_sample();
+    let sub = tree.split(ids[1]).unwrap();
+    assert_eq!(objs(&sub), vec![30, 40]);
+    assert_eq!(sub.parent(sub.root()), None);
+    assert_eq!(sub.depth(sub.children(sub.root()).unwrap()[0]), Some(2));
+    assert_eq!(objs(&tree), vec![20, 50]);
+    assert_eq!(tree.size(), 2);
+    assert_eq!(tree.get(ids[2]), None);
+
+    // the root stays, freed slots are reused
+    assert!(tree.split(tree.root()).is_none());
+    let root = tree.root();
+    tree.add(root, sub).unwrap();
+    assert_eq!(objs(&tree), vec![20, 50, 30, 40]);
+    assert_eq!(tree.nodes.len(), 4);
+}
+
+#[test]
+fn stale_ids_find_nothing() {
+    let (mut tree, ids) = obj_sample();
+    let (root, thirty, forty) = (ids[0], ids[1], ids[2]);
+    let sub = tree.split(thirty).unwrap();
+    // the new nodes land in the slots 30 and 40 left behind
+    let again = tree.add(root, sub).unwrap();
+    assert_eq!(tree.nodes.len(), 4);
+    assert!(again != thirty);
+    for stale in &[thirty, forty] {
+        assert_eq!(tree.get(*stale), None);
+        assert_eq!(tree.parent(*stale), None);
+        assert_eq!(tree.replace(*stale, 1), None);
+        assert!(tree.split(*stale).is_none());
+        assert!(tree.add(*stale, ObjTree::new(1)).is_err());
+    }
+    assert_eq!(tree.get(again), Some(&30));
+    assert_eq!(objs(&tree), vec![20, 50, 30, 40]);
+}
+
+#[test]
+fn subtree_and_replace() {
+    let (mut tree, ids) = obj_sample();
+    let before = *tree.root_hash();
+    let copy = tree.subtree(ids[1]).unwrap();
+    assert_eq!(objs(&copy), vec![30, 40]);
+    assert_eq!(copy.parent(copy.root()), None);
+    assert_eq!(copy.root_hash(), tree.get_tree_hash(ids[1]).unwrap());
+    assert_eq!(*tree.root_hash(), before);
+    assert!(tree.subtree(MISSING).is_none());
+
+    assert_eq!(tree.replace(ids[2], 45), Some(40));
+    assert_eq!(objs(&tree), vec![20, 30, 45, 50]);
+    assert!(*tree.root_hash() != before);
+    assert_eq!(
+        tree.get_obj_hash(ids[2]),
+        Some(&ObjHash(SipTreeHasher::hash_obj(&45)))
+    );
+    tree.replace(ids[2], 40);
+    assert_eq!(*tree.root_hash(), before);
+    assert_eq!(tree.replace(MISSING, 1), None);
+}
+
+#[test]
+fn children_parent_and_siblings() {
+    let (tree, ids) = obj_sample();
+    let (root, thirty, forty, fifty) = (ids[0], ids[1], ids[2], ids[3]);
+    assert_eq!(tree.children(root), Some(&[thirty, fifty][..]));
+    assert_eq!(tree.children(forty), Some(&[][..]));
+    assert_eq!(tree.parent(forty), Some(thirty));
+    assert_eq!(tree.parent(root), None);
+    assert_eq!(tree.siblings(thirty), Some(vec![fifty]));
+    assert_eq!(tree.siblings(forty), Some(vec![]));
+    assert_eq!(tree.siblings(root), Some(vec![]));
+}
+
+#[test]
+fn ancestors_and_neighbours() {
+    let (mut tree, ids) = obj_sample();
+    let (root, thirty, forty, fifty) = (ids[0], ids[1], ids[2], ids[3]);
+    assert_eq!(tree.ancestors(forty).collect::<Vec<_>>(), vec![thirty, root]);
+    assert_eq!(tree.ancestors(fifty).collect::<Vec<_>>(), vec![root]);
+    assert_eq!(tree.ancestors(root).next(), None);
+    assert_eq!(tree.ancestors(MISSING).next(), None);
+
+    let thirty_five = tree.insert_child(root, 1, ObjTree::new(35)).unwrap();
+    assert_eq!(tree.next_sibling(thirty), Some(thirty_five));
+    assert_eq!(tree.next_sibling(thirty_five), Some(fifty));
+    assert_eq!(tree.next_sibling(fifty), None);
+    assert_eq!(tree.prev_sibling(fifty), Some(thirty_five));
+    assert_eq!(tree.prev_sibling(thirty), None);
+    assert_eq!(tree.next_sibling(forty), None);
+    assert_eq!(tree.prev_sibling(root), None);
+    assert_eq!(tree.next_sibling(MISSING), None);
+
+    // links follow a subtree moved elsewhere
+    let sub = tree.split(thirty).unwrap();
+    let id = tree.add(thirty_five, sub).unwrap();
+    let forty = tree.children(id).unwrap()[0];
+    assert_eq!(tree.ancestors(forty).collect::<Vec<_>>(), vec![id, thirty_five, root]);
+    assert_eq!(tree.depth(forty), Some(4));
+    assert_eq!(tree.prev_sibling(thirty_five), None);
+}
+
+#[test]
+fn paths_and_nodes() {
+    let (tree, ids) = obj_sample();
+    assert_eq!(tree.path_of(ids[0]), Some(vec![]));
+    assert_eq!(tree.path_of(ids[2]), Some(vec![0, 0]));
+    assert_eq!(tree.path_of(ids[3]), Some(vec![1]));
+    for id in &ids {
+        assert_eq!(tree.node_at(&tree.path_of(*id).unwrap()), Some(*id));
+    }
+    assert_eq!(tree.node_at(&[2]), None);
+    assert_eq!(tree.node_at(&[1, 0]), None);
+    assert_eq!(tree.path_of(MISSING), None);
+}
+
+#[test]
+fn degree_depth_and_height() {
+    let (tree, ids) = obj_sample();
+    assert_eq!(tree.degree(ids[0]), Some(2));
+    assert_eq!(tree.degree(ids[2]), Some(0));
+    assert_eq!(tree.depth(ids[0]), Some(1));
+    assert_eq!(tree.depth(ids[2]), Some(3));
+    assert_eq!(tree.height(ids[0]), Some(3));
+    assert_eq!(tree.height(ids[3]), Some(1));
+    assert_eq!(tree.depth(MISSING), None);
+}
+
+#[test]
+fn hashes_follow_children() {
+    let (mut tree, ids) = obj_sample();
+    let before = *tree.root_hash();
+    assert_eq!(
+        tree.get_obj_hash(ids[1]),
+        Some(&ObjHash(SipTreeHasher::hash_obj(&30)))
+    );
+    tree.split(ids[3]);
+    assert!(*tree.root_hash() != before);
+
+    let (a, _) = obj_sample();
+    let (b, _) = obj_sample();
+    let mut ha = DefaultHasher::new();
+    let mut hb = DefaultHasher::new();
+    a.hash(&mut ha);
+    b.hash(&mut hb);
+    assert_eq!(ha.finish(), hb.finish());
+}
+
+#[test]
+fn deep_obj_tree() {
+    let mut tree = ObjTree::new(0);
+    let last = tree.batch(|tree| {
+        let mut last = tree.root();
+        for v in 1..100_000 {
+            last = tree.add(last, ObjTree::new(v)).unwrap();
+        }
+        last
+    });
+    assert_eq!(tree.depth(last), Some(100_000));
+    assert_eq!(tree.height(tree.root()), Some(100_000));
+    let deep = tree.split(last).unwrap();
+    assert_eq!(deep.size(), 1);
+    assert_eq!(tree.iter_pre_order().count(), 99_999);
+}
+
+#[test]
+fn batch_matches_edits_one_at_a_time() {
+    let edit = |tree: &mut ObjTree<i32>| {
+        let root = tree.root();
+        let wide = tree.add(root, ObjTree::new(1)).unwrap();
+        for v in 0..50 {
+            tree.add(wide, ObjTree::new(v)).unwrap();
+        }
+        let chain = tree.add(root, ObjTree::new(2)).unwrap();
+        let mut last = chain;
+        for v in 0..50 {
+            last = tree.add(last, ObjTree::new(v)).unwrap();
+        }
+        let third = tree.children(wide).unwrap()[3];
+        let sub = tree.split(third).unwrap();
+        assert_eq!(*sub.root_hash(), *ObjTree::new(3).root_hash());
+        tree.replace(last, 99);
+        tree.insert_child(chain, 0, sub).unwrap();
+        let copy = tree.subtree(wide).unwrap();
+        assert_eq!(copy.size(), 50);
+        copy
+    };
+    let mut one_by_one = ObjTree::new(0);
+    let expected = edit(&mut one_by_one);
+    let mut batched = ObjTree::new(0);
+    let copy = batched.batch(|tree| tree.batch(edit));
+    assert_eq!(*copy.root_hash(), *expected.root_hash());
+    assert_eq!(*batched.root_hash(), *one_by_one.root_hash());
+    for (id, _) in one_by_one.iter_pre_order() {
+        let path = one_by_one.path_of(id).unwrap();
+        let same = batched.node_at(&path).unwrap();
+        assert_eq!(batched.get_tree_hash(same), one_by_one.get_tree_hash(id));
+    }
+}
+
+#[test]
+fn batch_catches_up_after_a_panic() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let (mut tree, ids) = obj_sample();
+    let (expected, _) = obj_sample();
+    let mut expected = expected;
+    expected.replace(ids[2], 41);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        tree.batch(|tree| {
+            tree.replace(ids[2], 41);
+            panic!("edit failed");
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(*tree.root_hash(), *expected.root_hash());
+    tree.replace(ids[2], 40);
+    assert_eq!(*tree.root_hash(), *obj_sample().0.root_hash());
+}
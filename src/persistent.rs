@@ -0,0 +1,670 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Persistent (immutable) binary search tree with structural sharing
+//
+//
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::ops::RangeBounds;
+
+use super::{above_start, below_end};
+use super::SearchTree;
+
+#[cfg(not(feature = "sync"))]
+use std::rc::Rc as Link;
+#[cfg(feature = "sync")]
+use std::sync::Arc as Link;
+
+type PLink<V> = Option<Link<PNode<V>>>;
+
+///
+/// Persistent Binary Search Tree
+///
+/// Every update returns a new version of the tree and leaves the old one untouched.
+/// Only the nodes on the path from the root to the change are copied, all other
+/// subtrees are shared between versions. Cloning a version is O(1).
+///
+/// The tree is kept AVL balanced, so that path, and with it the cost of an update,
+/// is O(log n) whatever order the values arrive in.
+///
+/// Nodes are reference counted with `Rc`. Building with the `sync` feature switches
+/// to `Arc` so versions are `Send + Sync` and can be handed to other threads.
+///
+pub struct PersistentBST<V> {
+    root: PLink<V>,
+    size: usize,
+}
+
+struct PNode<V> {
+    val: V,
+    left: PLink<V>,
+    right: PLink<V>,
+    height: usize,
+}
+
+fn height<V>(link: &PLink<V>) -> usize {
+    link.as_ref().map_or(0, |n| n.height)
+}
+
+// A new node over two subtrees whose heights differ by at most one
+fn node<V>(val: V, left: PLink<V>, right: PLink<V>) -> PLink<V> {
+    let height = 1 + height(&left).max(height(&right));
+    Some(Link::new(PNode {
+        val,
+        left,
+        right,
+        height,
+    }))
+}
+
+// A new node over two subtrees whose heights differ by at most two, rotated back
+// into balance. The rotations copy at most three nodes, so an update still only
+// copies the path it walked.
+fn balance<V: Copy>(val: V, left: PLink<V>, right: PLink<V>) -> PLink<V> {
+    let (hl, hr) = (height(&left), height(&right));
+    if hl > hr + 1 {
+        let l = left.as_ref().unwrap();
+        if height(&l.left) >= height(&l.right) {
+            node(l.val, l.left.clone(), node(val, l.right.clone(), right))
+        } else {
+            let lr = l.right.as_ref().unwrap();
+            node(
+                lr.val,
+                node(l.val, l.left.clone(), lr.left.clone()),
+                node(val, lr.right.clone(), right),
+            )
+        }
+    } else if hr > hl + 1 {
+        let r = right.as_ref().unwrap();
+        if height(&r.right) >= height(&r.left) {
+            node(r.val, node(val, left, r.left.clone()), r.right.clone())
+        } else {
+            let rl = r.left.as_ref().unwrap();
+            node(
+                rl.val,
+                node(val, left, rl.left.clone()),
+                node(r.val, rl.right.clone(), r.right.clone()),
+            )
+        }
+    } else {
+        node(val, left, right)
+    }
+}
+
+impl<V> Clone for PersistentBST<V> {
+    fn clone(&self) -> Self {
+        PersistentBST {
+            root: self.root.clone(),
+            size: self.size,
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++Iterators+++++++++++++++++++++++++++++++++++++
+
+///
+/// In order iteration of a PersistentBST.
+///
+pub struct PersistentInOrderIter<'a, V: 'a> {
+    iter_stack: Vec<&'a PNode<V>>,
+}
+
+impl<'a, V: 'a> PersistentInOrderIter<'a, V> {
+    fn push_leftmost(&mut self, mut link: &'a PLink<V>) {
+        while let Some(ref node) = *link {
+            self.iter_stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, V> Iterator for PersistentInOrderIter<'a, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let node = self.iter_stack.pop()?;
+        self.push_leftmost(&node.right);
+        Some(&node.val)
+    }
+}
+
+///
+/// In order iteration of the values of a PersistentBST inside a range.
+///
+pub struct PersistentRange<'a, V: 'a, R> {
+    iter: PersistentInOrderIter<'a, V>,
+    range: R,
+}
+
+impl<'a, V, R> Iterator for PersistentRange<'a, V, R>
+where
+    V: Ord,
+    R: RangeBounds<V>,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let val = self.iter.next()?;
+        if below_end(&self.range, val) {
+            Some(val)
+        } else {
+            // past the end, nothing later can be in range
+            self.iter.iter_stack.clear();
+            None
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-PersistentBST+++++++++++++++++++++++++++++
+impl<V> PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    pub fn new() -> Self {
+        PersistentBST {
+            root: None,
+            size: 0,
+        }
+    }
+
+    ///
+    /// Returns a new version containing `new_val`. If the value is already present
+    /// the returned version shares the whole tree with `self`.
+    ///
+    pub fn insert(&self, new_val: V) -> Self {
+        match Self::insert_node(&self.root, new_val) {
+            None => self.clone(),
+            Some(root) => PersistentBST {
+                root,
+                size: self.size + 1,
+            },
+        }
+    }
+
+    // Returns None when the value is already present so nothing has to be copied
+    fn insert_node(link: &PLink<V>, new_val: V) -> Option<PLink<V>> {
+        match *link {
+            None => Some(node(new_val, None, None)),
+            Some(ref n) => match new_val.cmp(&n.val) {
+                Ordering::Equal => None,
+                Ordering::Less => Self::insert_node(&n.left, new_val)
+                    .map(|left| balance(n.val, left, n.right.clone())),
+                Ordering::Greater => Self::insert_node(&n.right, new_val)
+                    .map(|right| balance(n.val, n.left.clone(), right)),
+            },
+        }
+    }
+
+    ///
+    /// Returns a new version without `val`. If the value is not present the
+    /// returned version shares the whole tree with `self`.
+    ///
+    pub fn remove(&self, val: V) -> Self {
+        match Self::remove_node(&self.root, val) {
+            None => self.clone(),
+            Some(root) => PersistentBST {
+                root,
+                size: self.size - 1,
+            },
+        }
+    }
+
+    // Returns None when the value is not found so nothing has to be copied
+    fn remove_node(link: &PLink<V>, val: V) -> Option<PLink<V>> {
+        let n = match *link {
+            None => return None,
+            Some(ref n) => n,
+        };
+        match val.cmp(&n.val) {
+            Ordering::Less => Self::remove_node(&n.left, val)
+                .map(|left| balance(n.val, left, n.right.clone())),
+            Ordering::Greater => Self::remove_node(&n.right, val)
+                .map(|right| balance(n.val, n.left.clone(), right)),
+            Ordering::Equal => match (&n.left, &n.right) {
+                (&None, _) => Some(n.right.clone()),
+                (_, &None) => Some(n.left.clone()),
+                (_, Some(r)) => {
+                    let (min, right) = Self::remove_min(r);
+                    Some(balance(min, n.left.clone(), right))
+                }
+            },
+        }
+    }
+
+    // Removes the leftmost node of a subtree, returning its value and the new subtree
+    fn remove_min(node: &Link<PNode<V>>) -> (V, PLink<V>) {
+        match node.left {
+            None => (node.val, node.right.clone()),
+            Some(ref l) => {
+                let (min, left) = Self::remove_min(l);
+                (min, balance(node.val, left, node.right.clone()))
+            }
+        }
+    }
+
+    ///
+    /// returns an option of generic type V. None or Some(V).
+    ///
+    pub fn find(&self, val: V) -> Option<V> {
+        self.lookup(&val).cloned()
+    }
+
+    fn lookup(&self, val: &V) -> Option<&V> {
+        let mut link = &self.root;
+        while let Some(ref n) = *link {
+            match val.cmp(&n.val) {
+                Ordering::Equal => return Some(&n.val),
+                Ordering::Less => link = &n.left,
+                Ordering::Greater => link = &n.right,
+            }
+        }
+        None
+    }
+
+    ///
+    /// If tree contains generic type V. Returns true. Otherwise returns false.
+    ///
+    pub fn contains(&self, val: V) -> bool {
+        self.find(val).is_some()
+    }
+
+    ///
+    /// Returns tree's minimum value
+    ///
+    pub fn min_value(&self) -> Option<V> {
+        self.first().cloned()
+    }
+
+    fn first(&self) -> Option<&V> {
+        let mut link = &self.root;
+        let mut min = None;
+        while let Some(ref n) = *link {
+            min = Some(&n.val);
+            link = &n.left;
+        }
+        min
+    }
+
+    ///
+    /// Returns tree's maximum value
+    ///
+    pub fn max_value(&self) -> Option<V> {
+        self.last().cloned()
+    }
+
+    fn last(&self) -> Option<&V> {
+        let mut link = &self.root;
+        let mut max = None;
+        while let Some(ref n) = *link {
+            max = Some(&n.val);
+            link = &n.right;
+        }
+        max
+    }
+
+    ///
+    /// returns size of tree
+    ///
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    ///
+    /// Gets the value the root.
+    ///
+    pub fn peek(&self) -> Option<&V> {
+        self.root.as_ref().map(|n| &n.val)
+    }
+
+    pub fn iter_in_order(&self) -> PersistentInOrderIter<'_, V> {
+        let mut iter = PersistentInOrderIter {
+            iter_stack: Vec::new(),
+        };
+        iter.push_leftmost(&self.root);
+        iter
+    }
+
+    ///
+    /// The values inside `range` in order. Starts at the first of them, so the
+    /// cost is O(log n) plus the values returned.
+    ///
+    pub fn range<R: RangeBounds<V>>(&self, range: R) -> PersistentRange<'_, V, R> {
+        let mut iter = PersistentInOrderIter {
+            iter_stack: Vec::new(),
+        };
+        let mut link = &self.root;
+        while let Some(ref n) = *link {
+            if above_start(&range, &n.val) {
+                iter.iter_stack.push(n);
+                link = &n.left;
+            } else {
+                link = &n.right;
+            }
+        }
+        PersistentRange { iter, range }
+    }
+
+    ///
+    /// Returns true if both versions share the same root node.
+    ///
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (&None, &None) => true,
+            (Some(a), Some(b)) => Link::ptr_eq(a, b),
+            (_, _) => false,
+        }
+    }
+}
+
+impl<V> Default for PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    fn default() -> Self {
+        PersistentBST::new()
+    }
+}
+
+///
+/// Updates through the trait replace `self` with the new version, so earlier
+/// clones keep seeing the tree as it was.
+///
+impl<V> SearchTree<V> for PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    fn insert(&mut self, val: V) -> bool {
+        let next = PersistentBST::insert(self, val);
+        let inserted = next.size != self.size;
+        *self = next;
+        inserted
+    }
+
+    fn remove(&mut self, val: &V) -> bool {
+        let next = PersistentBST::remove(self, *val);
+        let removed = next.size != self.size;
+        *self = next;
+        removed
+    }
+
+    fn get(&self, val: &V) -> Option<&V> {
+        self.lookup(val)
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn min(&self) -> Option<&V> {
+        self.first()
+    }
+
+    fn max(&self) -> Option<&V> {
+        self.last()
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a,
+    {
+        Box::new(self.iter_in_order())
+    }
+
+    fn range<'a, R>(&'a self, range: R) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a,
+        R: RangeBounds<V> + 'a,
+    {
+        Box::new(PersistentBST::range(self, range))
+    }
+}
+
+///
+/// Builds a balanced tree from any values, dropping duplicates.
+///
+impl<V> FromIterator<V> for PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+{
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        let mut values: Vec<V> = iter.into_iter().collect();
+        values.sort();
+        values.dedup();
+        PersistentBST {
+            root: from_sorted(&values),
+            size: values.len(),
+        }
+    }
+}
+
+fn from_sorted<V: Copy>(values: &[V]) -> PLink<V> {
+    if values.is_empty() {
+        return None;
+    }
+    let mid = values.len() / 2;
+    node(
+        values[mid],
+        from_sorted(&values[..mid]),
+        from_sorted(&values[mid + 1..]),
+    )
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use std::ops::Bound;
+
+#[cfg(test)]
+use super::SAMPLE;
+
+#[cfg(test)]
+fn sample_version() -> PersistentBST<i32> {
+    let mut tree = PersistentBST::new();
+    for v in &SAMPLE {
+        tree = tree.insert(*v);
+    }
+    tree
+}
+
+#[test]
+fn persistent_insert_keeps_old_version() {
+    let tree = sample_version();
+    let tree2 = tree.insert(9);
+
+    let old: Vec<i32> = tree.iter_in_order().cloned().collect();
+    let new: Vec<i32> = tree2.iter_in_order().cloned().collect();
+    assert_eq!(old, vec![1, 6, 7, 8, 10, 13, 20]);
+    assert_eq!(new, vec![1, 6, 7, 8, 9, 10, 13, 20]);
+    assert_eq!(tree.size(), 7);
+    assert_eq!(tree2.size(), 8);
+    assert!(!tree.contains(9));
+    assert!(tree2.contains(9));
+}
+
+#[test]
+fn persistent_insert_shares_untouched_subtrees() {
+    let tree = sample_version();
+    let tree2 = tree.insert(9);
+
+    let (root1, root2) = (tree.root.as_ref().unwrap(), tree2.root.as_ref().unwrap());
+    assert!(!Link::ptr_eq(root1, root2));
+    // 9 goes right of the root, so the whole left subtree is shared
+    assert!(Link::ptr_eq(
+        root1.left.as_ref().unwrap(),
+        root2.left.as_ref().unwrap()
+    ));
+    // and so is the right subtree of 13
+    let (r1, r2) = (root1.right.as_ref().unwrap(), root2.right.as_ref().unwrap());
+    assert!(Link::ptr_eq(r1.right.as_ref().unwrap(), r2.right.as_ref().unwrap()));
+}
+
+#[test]
+fn persistent_insert_existing_shares_everything() {
+    let tree = sample_version();
+    let tree2 = tree.insert(10);
+    assert!(tree.ptr_eq(&tree2));
+    assert_eq!(tree2.size(), 7);
+}
+
+#[test]
+fn persistent_remove() {
+    let tree = sample_version();
+
+    // leaf, one child and two children
+    let no_leaf = tree.remove(1);
+    let no_six = tree.remove(6);
+    let no_root = tree.remove(8);
+
+    assert_eq!(
+        no_leaf.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![6, 7, 8, 10, 13, 20]
+    );
+    assert_eq!(
+        no_six.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![1, 7, 8, 10, 13, 20]
+    );
+    assert_eq!(
+        no_root.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![1, 6, 7, 10, 13, 20]
+    );
+    assert_eq!(no_root.peek(), Some(&10));
+    assert_eq!(no_root.size(), 6);
+
+    // the original is untouched
+    assert_eq!(
+        tree.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![1, 6, 7, 8, 10, 13, 20]
+    );
+
+    // removing from the left leaves the right subtree shared
+    assert!(Link::ptr_eq(
+        tree.root.as_ref().unwrap().right.as_ref().unwrap(),
+        no_leaf.root.as_ref().unwrap().right.as_ref().unwrap()
+    ));
+}
+
+#[test]
+fn persistent_range() {
+    let tree = sample_version();
+    let range = |r: (Bound<i32>, Bound<i32>)| tree.range(r).cloned().collect::<Vec<i32>>();
+    assert_eq!(range((Bound::Included(6), Bound::Excluded(13))), vec![6, 7, 8, 10]);
+    assert_eq!(range((Bound::Excluded(7), Bound::Included(13))), vec![8, 10, 13]);
+    assert_eq!(range((Bound::Unbounded, Bound::Included(1))), vec![1]);
+    assert_eq!(range((Bound::Included(14), Bound::Unbounded)), vec![20]);
+    assert_eq!(range((Bound::Included(9), Bound::Excluded(9))), Vec::<i32>::new());
+    assert_eq!(tree.range(..).count(), 7);
+    assert_eq!(PersistentBST::<i32>::new().range(..).next(), None);
+}
+
+#[test]
+fn persistent_remove_missing_shares_everything() {
+    let tree = sample_version();
+    let tree2 = tree.remove(50);
+    assert!(tree.ptr_eq(&tree2));
+    assert_eq!(tree2.size(), 7);
+}
+
+#[test]
+fn persistent_clone_is_shallow() {
+    let tree = sample_version();
+    let copy = tree.clone();
+    assert!(tree.ptr_eq(&copy));
+    assert_eq!(copy.min_value(), Some(1));
+    assert_eq!(copy.max_value(), Some(20));
+    assert_eq!(copy.find(13), Some(13));
+    assert_eq!(copy.find(14), None);
+}
+
+#[test]
+fn persistent_undo_history() {
+    let mut history = vec![PersistentBST::new()];
+    for v in 0..10 {
+        let next = history.last().unwrap().insert(v);
+        history.push(next);
+    }
+    for (i, version) in history.iter().enumerate() {
+        assert_eq!(version.size(), i);
+        assert_eq!(
+            version.iter_in_order().cloned().collect::<Vec<i32>>(),
+            (0..i as i32).collect::<Vec<i32>>()
+        );
+    }
+}
+
+#[test]
+fn persistent_from_iter_is_balanced() {
+    let tree: PersistentBST<i32> = (0..15).rev().chain(3..9).collect();
+    assert_eq!(tree.size(), 15);
+    assert_eq!(tree.peek(), Some(&7));
+    assert_eq!(
+        tree.iter_in_order().cloned().collect::<Vec<i32>>(),
+        (0..15).collect::<Vec<i32>>()
+    );
+}
+
+// Checks the AVL invariants and the stored heights, returning the height
+#[cfg(test)]
+fn check_balanced<V>(link: &PLink<V>) -> usize {
+    match *link {
+        None => 0,
+        Some(ref n) => {
+            let (hl, hr) = (check_balanced(&n.left), check_balanced(&n.right));
+            assert!(hl <= hr + 1 && hr <= hl + 1);
+            assert_eq!(n.height, 1 + hl.max(hr));
+            n.height
+        }
+    }
+}
+
+#[test]
+fn persistent_stays_balanced() {
+    // sorted input would make a chain without rebalancing
+    let mut tree = PersistentBST::new();
+    for v in 0..100_000u32 {
+        tree = tree.insert(v);
+    }
+    let first = tree.clone();
+    assert_eq!(check_balanced(&tree.root), 17);
+
+    for v in (0..100_000u32).filter(|v| v % 3 != 0) {
+        tree = tree.remove(v);
+    }
+    assert_eq!(tree.size(), 33_334);
+    assert!(check_balanced(&tree.root) <= 17);
+    assert!(tree.iter_in_order().cloned().eq((0..100_000u32).step_by(3)));
+    assert_eq!(first.size(), 100_000);
+    check_balanced(&first.root);
+
+    let mut down = PersistentBST::new();
+    for v in (0..1000).rev() {
+        down = down.insert(v);
+    }
+    assert!(check_balanced(&down.root) <= 11);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn persistent_snapshot_across_threads() {
+    use std::thread;
+
+    let tree = sample_version();
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let snapshot = tree.clone();
+            thread::spawn(move || {
+                let local = snapshot.insert(100 + i);
+                assert_eq!(snapshot.size(), 7);
+                local.size()
+            })
+        })
+        .collect();
+    for h in handles {
+        assert_eq!(h.join().unwrap(), 8);
+    }
+    assert_eq!(tree.size(), 7);
+}
@@ -0,0 +1,576 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Text rendering of tree shapes for terminals
+//
+//
+
+use std::cmp;
+use std::fmt;
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Summary, BST};
+
+///
+/// How `pretty` lays out the tree.
+///
+/// `Sideways` puts the root on the left with right subtrees above and left
+/// subtrees below it, one value per line, so it works for trees of any width.
+/// `TopDown` draws the usual picture with the root on top, which reads better but
+/// grows wide quickly.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Sideways,
+    TopDown,
+}
+
+///
+/// Options for `pretty`.
+///
+/// Subtrees below `max_depth` are folded into a single `… (n)` entry giving the
+/// number of values they hold. Lines longer than `max_width` characters are cut
+/// and end in `…`. `ascii` draws with plain ASCII instead of box-drawing characters.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyOptions {
+    pub layout: Layout,
+    pub max_depth: Option<usize>,
+    pub max_width: Option<usize>,
+    pub ascii: bool,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            layout: Layout::Sideways,
+            max_depth: None,
+            max_width: None,
+            ascii: false,
+        }
+    }
+}
+
+struct Glyphs {
+    upper: &'static str,
+    lower: &'static str,
+    bar: &'static str,
+    blank: &'static str,
+    left_corner: char,
+    right_corner: char,
+    line: char,
+    more: &'static str,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    upper: "┌── ",
+    lower: "└── ",
+    bar: "│   ",
+    blank: "    ",
+    left_corner: '┌',
+    right_corner: '┐',
+    line: '─',
+    more: "…",
+};
+
+const ASCII: Glyphs = Glyphs {
+    upper: "/-- ",
+    lower: "\\-- ",
+    bar: "|   ",
+    blank: "    ",
+    left_corner: '/',
+    right_corner: '\\',
+    line: '-',
+    more: "...",
+};
+
+// Where a node sits relative to its parent in the sideways layout
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Root,
+    Upper,
+    Lower,
+}
+
+impl Side {
+    fn connector(self, glyphs: &Glyphs) -> &'static str {
+        match self {
+            Side::Root => "",
+            Side::Upper => glyphs.upper,
+            Side::Lower => glyphs.lower,
+        }
+    }
+}
+
+enum Step<'a, V: 'a, S: 'a> {
+    Visit(&'a BSTNode<V, S>, Side, usize),
+    Line(&'static str, String),
+    Enter(&'static str),
+    Leave,
+}
+
+fn label<V: Debug, S>(node: &BSTNode<V, S>) -> String {
+    match node.val {
+        Some(ref v) => format!("{:?}", v),
+        None => "-".to_string(),
+    }
+}
+
+// One output line, written left to right in pieces placed at known columns.
+// Only the first `max_width + 1` characters are kept, and `end` tracks the column
+// after the last character that isn't a space, which is all `finish` needs to
+// clip the line. Runs of spaces are only written out when something follows them.
+struct Row {
+    text: String,
+    shown: usize,
+    len: usize,
+    end: usize,
+    max_width: Option<usize>,
+}
+
+impl Row {
+    fn new(max_width: Option<usize>) -> Row {
+        Row {
+            text: String::new(),
+            shown: 0,
+            len: 0,
+            end: 0,
+            max_width,
+        }
+    }
+
+    fn cap(&self) -> usize {
+        self.max_width.map_or(usize::MAX, |w| w + 1)
+    }
+
+    // Nothing written from here on can show up in the clipped line
+    fn full(&self) -> bool {
+        self.len >= self.cap()
+    }
+
+    fn move_to(&mut self, col: usize) {
+        self.len = cmp::max(self.len, col);
+    }
+
+    fn put(&mut self, s: &str) {
+        let trimmed = s.trim_end();
+        if !trimmed.is_empty() {
+            self.end = self.len + trimmed.chars().count();
+            let start = self.len;
+            self.fill(start, trimmed.chars());
+        }
+        self.len += s.chars().count();
+    }
+
+    fn run(&mut self, c: char, n: usize) {
+        if n > 0 {
+            self.end = self.len + n;
+            let start = self.len;
+            self.fill(start, (0..n).map(|_| c));
+        }
+        self.len += n;
+    }
+
+    fn fill<I: Iterator<Item = char>>(&mut self, col: usize, chars: I) {
+        let cap = self.cap();
+        while self.shown < cmp::min(col, cap) {
+            self.text.push(' ');
+            self.shown += 1;
+        }
+        for c in chars {
+            if self.shown >= cap {
+                break;
+            }
+            self.text.push(c);
+            self.shown += 1;
+        }
+    }
+
+    // Cuts the line to `max_width` characters, ending it in `more` if anything is lost
+    fn finish(self, more: &str) -> String {
+        match self.max_width {
+            Some(w) if self.end > w => {
+                let keep = w.saturating_sub(more.chars().count());
+                self.text
+                    .chars()
+                    .take(keep)
+                    .chain(more.chars())
+                    .take(w)
+                    .collect()
+            }
+            _ => self.text,
+        }
+    }
+}
+
+// A node of the top-down layout, in level order. `width` and `middle` give the
+// extent of its subtree's block and the column of its text, and `x` the column
+// where that block starts.
+struct Slot {
+    text: String,
+    level: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+    width: usize,
+    middle: usize,
+    x: usize,
+}
+
+impl Slot {
+    fn new(text: String, level: usize) -> Slot {
+        Slot {
+            text,
+            level,
+            left: None,
+            right: None,
+            width: 0,
+            middle: 0,
+            x: 0,
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Draws the tree as text, one line per row, without a trailing newline.
+    ///
+    /// Lines are cut to `max_width` while they are drawn, so a narrow drawing of
+    /// a deep tree takes time in proportion to the number of values rather than
+    /// to the square of the depth.
+    ///
+    pub fn pretty(&self, options: &PrettyOptions) -> String {
+        let glyphs = if options.ascii { &ASCII } else { &UNICODE };
+        let lines = match (options.layout, self) {
+            (_, Empty) => Vec::new(),
+            (Layout::Sideways, NonEmpty(n)) => Self::sideways(n, options, glyphs),
+            (Layout::TopDown, NonEmpty(n)) => Self::top_down(n, options, glyphs),
+        };
+        lines.join("\n")
+    }
+
+    fn folded(tree: &BST<V, S>, glyphs: &Glyphs) -> String {
+        format!("{} ({})", glyphs.more, tree.size())
+    }
+
+    fn sideways(root: &BSTNode<V, S>, options: &PrettyOptions, glyphs: &Glyphs) -> Vec<String> {
+        let mut lines = Vec::new();
+        // Prefix pieces of the current line, each with the column it ends at
+        let mut path: Vec<(&'static str, usize)> = Vec::new();
+        // Right subtree first, then the node, then the left subtree
+        let mut stack = vec![Step::Visit(root, Side::Root, 1)];
+        while let Some(step) = stack.pop() {
+            let (node, side, depth) = match step {
+                Step::Visit(node, side, depth) => (node, side, depth),
+                Step::Line(connector, text) => {
+                    let mut row = Row::new(options.max_width);
+                    for &(piece, _) in &path {
+                        if row.full() {
+                            break;
+                        }
+                        row.put(piece);
+                    }
+                    row.move_to(path.last().map_or(0, |&(_, end)| end));
+                    row.put(connector);
+                    row.put(&text);
+                    lines.push(row.finish(glyphs.more));
+                    continue;
+                }
+                Step::Enter(piece) => {
+                    let end = path.last().map_or(0, |&(_, end)| end) + piece.chars().count();
+                    path.push((piece, end));
+                    continue;
+                }
+                Step::Leave => {
+                    path.pop();
+                    continue;
+                }
+            };
+            let (right_piece, left_piece) = match side {
+                Side::Root => ("", ""),
+                Side::Upper => (glyphs.blank, glyphs.bar),
+                Side::Lower => (glyphs.bar, glyphs.blank),
+            };
+            let folding = options.max_depth.is_some_and(|d| depth >= d);
+
+            Self::push_child(
+                &mut stack,
+                &node.left,
+                left_piece,
+                Side::Lower,
+                depth,
+                folding,
+                glyphs,
+            );
+            stack.push(Step::Line(side.connector(glyphs), label(node)));
+            Self::push_child(
+                &mut stack,
+                &node.right,
+                right_piece,
+                Side::Upper,
+                depth,
+                folding,
+                glyphs,
+            );
+        }
+        lines
+    }
+
+    fn push_child<'a>(
+        stack: &mut Vec<Step<'a, V, S>>,
+        tree: &'a BST<V, S>,
+        piece: &'static str,
+        side: Side,
+        depth: usize,
+        folding: bool,
+        glyphs: &Glyphs,
+    ) {
+        if let NonEmpty(ref n) = *tree {
+            stack.push(Step::Leave);
+            if folding {
+                stack.push(Step::Line(
+                    side.connector(glyphs),
+                    Self::folded(tree, glyphs),
+                ));
+            } else {
+                stack.push(Step::Visit(n, side, depth + 1));
+            }
+            stack.push(Step::Enter(piece));
+        }
+    }
+
+    // Puts both subtrees side by side with the node centred above the gap. Block
+    // widths are worked out bottom-up, then each level is drawn as one row.
+    fn top_down(root: &BSTNode<V, S>, options: &PrettyOptions, glyphs: &Glyphs) -> Vec<String> {
+        let mut nodes = vec![Some(root)];
+        let mut slots = vec![Slot::new(label(root), 0)];
+        let mut i = 0;
+        while i < slots.len() {
+            if let Some(node) = nodes[i] {
+                let level = slots[i].level;
+                let folding = options.max_depth.is_some_and(|d| level + 1 >= d);
+                for &(tree, is_left) in &[(&node.left, true), (&node.right, false)] {
+                    let (text, next) = match *tree {
+                        Empty => continue,
+                        NonEmpty(_) if folding => (Self::folded(tree, glyphs), None),
+                        NonEmpty(ref n) => (label(n), Some(&**n)),
+                    };
+                    let child = Some(slots.len());
+                    if is_left {
+                        slots[i].left = child;
+                    } else {
+                        slots[i].right = child;
+                    }
+                    slots.push(Slot::new(text, level + 1));
+                    nodes.push(next);
+                }
+            }
+            i += 1;
+        }
+
+        // Children come after their parents, so walking backwards sizes them first
+        for i in (0..slots.len()).rev() {
+            let lw = slots[i].left.map_or(0, |l| slots[l].width);
+            let rw = slots[i].right.map_or(0, |r| slots[r].width);
+            let u = slots[i].text.chars().count();
+            slots[i].width = lw + u + rw;
+            slots[i].middle = lw + u / 2;
+        }
+        for i in 0..slots.len() {
+            let lw = slots[i].left.map_or(0, |l| slots[l].width);
+            let x = slots[i].x;
+            if let Some(l) = slots[i].left {
+                slots[l].x = x;
+            }
+            if let Some(r) = slots[i].right {
+                slots[r].x = x + lw + slots[i].text.chars().count();
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut row = Row::new(options.max_width);
+        let mut level = 0;
+        for slot in &slots {
+            if slot.level != level {
+                lines.push(row.finish(glyphs.more));
+                row = Row::new(options.max_width);
+                level = slot.level;
+            }
+            if row.full() {
+                continue;
+            }
+            let lw = slot.left.map_or(0, |l| slots[l].width);
+            if let Some(l) = slot.left {
+                let middle = slots[l].middle;
+                row.move_to(slot.x + middle);
+                row.run(glyphs.left_corner, 1);
+                row.run(glyphs.line, lw - middle - 1);
+            }
+            row.move_to(slot.x + lw);
+            row.put(&slot.text);
+            if let Some(r) = slot.right {
+                row.run(glyphs.line, slots[r].middle);
+                row.run(glyphs.right_corner, 1);
+            }
+        }
+        lines.push(row.finish(glyphs.more));
+        lines
+    }
+}
+
+///
+/// Draws the tree sideways with the default options.
+///
+impl<V, S> fmt::Display for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pretty(&PrettyOptions::default()))
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn pretty_display_sideways() {
+    let tree: BST<i32> = sample_tree();
+    let expected = [
+        "    ┌── 20",
+        "┌── 13",
+        "│   └── 10",
+        "8",
+        "│   ┌── 7",
+        "└── 6",
+        "    └── 1",
+    ];
+    assert_eq!(format!("{}", tree), expected.join("\n"));
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(format!("{}", empty), "");
+}
+
+#[test]
+fn pretty_top_down() {
+    let tree: BST<i32> = sample_tree();
+    let options = PrettyOptions {
+        layout: Layout::TopDown,
+        ..PrettyOptions::default()
+    };
+    let expected = [
+        " ┌─8───┐", //
+        "┌6┐  ┌13─┐",
+        "1 7 10  20",
+    ];
+    assert_eq!(tree.pretty(&options), expected.join("\n"));
+
+    let mut chain: BST<i32> = BST::new();
+    for v in &[3, 2, 1] {
+        chain.insert(*v);
+    }
+    assert_eq!(chain.pretty(&options), " ┌3\n┌2\n1");
+}
+
+#[test]
+fn pretty_ascii_and_depth_limit() {
+    let tree: BST<i32> = sample_tree();
+    let options = PrettyOptions {
+        max_depth: Some(2),
+        ascii: true,
+        ..PrettyOptions::default()
+    };
+    let expected = [
+        "    /-- ... (1)", //
+        "/-- 13",
+        "|   \\-- ... (1)",
+        "8",
+        "|   /-- ... (1)",
+        "\\-- 6",
+        "    \\-- ... (1)",
+    ];
+    assert_eq!(tree.pretty(&options), expected.join("\n"));
+}
+
+#[test]
+fn pretty_width_limit() {
+    let mut chain: BST<u32> = BST::new();
+    for v in 0..50 {
+        chain.insert(v);
+    }
+    let options = PrettyOptions {
+        max_width: Some(12),
+        ..PrettyOptions::default()
+    };
+    let out = chain.pretty(&options);
+    assert_eq!(out.lines().count(), 50);
+    assert!(out.lines().all(|l| l.chars().count() <= 12));
+    assert_eq!(out.lines().last(), Some("0"));
+    assert_eq!(out.lines().next(), Some("           …"));
+}
+
+#[test]
+fn pretty_width_limit_matches_clipped_lines() {
+    let mut tree: BST<i32> = sample_tree();
+    for v in &[3, 2, 4, 5, 9, 11, 12, 15, 14, 30, 25, 40] {
+        tree.insert(*v);
+    }
+    for &layout in &[Layout::Sideways, Layout::TopDown] {
+        for &max_depth in &[None, Some(3)] {
+            for &ascii in &[false, true] {
+                let mut options = PrettyOptions {
+                    layout,
+                    max_depth,
+                    max_width: None,
+                    ascii,
+                };
+                let full = tree.pretty(&options);
+                let more = if ascii { "..." } else { "…" };
+                for w in 0..40 {
+                    options.max_width = Some(w);
+                    let expected: Vec<String> = full
+                        .lines()
+                        .map(|l| {
+                            let l = l.trim_end();
+                            if l.chars().count() <= w {
+                                return l.to_string();
+                            }
+                            let keep = w.saturating_sub(more.chars().count());
+                            l.chars().take(keep).chain(more.chars()).take(w).collect()
+                        })
+                        .collect();
+                    assert_eq!(tree.pretty(&options), expected.join("\n"));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn pretty_deep_chain() {
+    // Built by hand, inserting sorted values is quadratic
+    let mut chain: BST<u32> = BST::new();
+    for v in (0..100_000).rev() {
+        let mut node = BST::leaf(v);
+        if let NonEmpty(ref mut n) = node {
+            n.right = chain;
+        }
+        chain = node;
+    }
+    for &layout in &[Layout::Sideways, Layout::TopDown] {
+        let options = PrettyOptions {
+            layout,
+            max_width: Some(20),
+            ..PrettyOptions::default()
+        };
+        let out = chain.pretty(&options);
+        assert_eq!(out.lines().count(), 100_000);
+        assert!(out.lines().all(|l| l.chars().count() <= 20));
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Merkle inclusion proofs for objects in an ObjTree
+//
+//
+
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
+
+use super::binary::Checksummed;
+use super::{Codec, FormatError, HashBytes, ObjTree, ObjTreeHash, SipTreeHasher, TreeHasher};
+
+///
+/// Proof layout, integers little endian as in the tree file format:
+///
+///   magic     4 bytes  "GTPF"
+///   version   u16  PROOF_FORMAT_VERSION, separate from the tree file's
+///   children  u64 count, then the tree hashes of the proven node's children
+///   steps     u64 count, then per step from the parent up to the root:
+///             its object hash, the u64 index of the child below it,
+///             u64 count and the tree hashes of that child's siblings
+///   checksum  u32  FNV-1a of every byte before it
+///
+/// Digests are written by their Codec.
+///
+pub const PROOF_MAGIC: [u8; 4] = *b"GTPF";
+
+///
+/// Version of the proof layout, bumped whenever the layout above changes.
+///
+pub const PROOF_FORMAT_VERSION: u16 = 2;
+
+// One ancestor of the proven node, enough to recompute its tree hash
+struct ProofStep<D> {
+    obj_hash: D,
+    index: usize,
+    siblings: Vec<D>,
+}
+
+impl<D: Clone> Clone for ProofStep<D> {
+    fn clone(&self) -> Self {
+        ProofStep {
+            obj_hash: self.obj_hash.clone(),
+            index: self.index,
+            siblings: self.siblings.clone(),
+        }
+    }
+}
+
+///
+/// Evidence that an object sits at a given place under a known root hash.
+///
+/// Holds the tree hashes of the object's children and, for every ancestor, its
+/// object hash and the tree hashes of the siblings on the path. That is enough to
+/// recompute the root hash from the object alone, see `verify`.
+///
+pub struct InclusionProof<H: TreeHasher = SipTreeHasher> {
+    children: Vec<H::Digest>,
+    steps: Vec<ProofStep<H::Digest>>,
+}
+
+impl<H: TreeHasher> Clone for InclusionProof<H> {
+    fn clone(&self) -> Self {
+        InclusionProof {
+            children: self.children.clone(),
+            steps: self.steps.clone(),
+        }
+    }
+}
+
+impl<H: TreeHasher> PartialEq for InclusionProof<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.children == other.children
+            && self.steps.len() == other.steps.len()
+            && self.steps.iter().zip(other.steps.iter()).all(|(a, b)| {
+                a.obj_hash == b.obj_hash && a.index == b.index && a.siblings == b.siblings
+            })
+    }
+}
+
+impl<H: TreeHasher> Debug for InclusionProof<H> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "InclusionProof {{ path: {:?}, children: {:?} }}",
+            self.path(),
+            self.children
+        )
+    }
+}
+
+///
+/// Checks that `proof` shows `obj` under a tree whose root hash is `root_hash`.
+///
+/// Recomputes the tree hashes from the object up to the root; any change to the
+/// object, the proof or the root makes the final comparison fail.
+///
+pub fn verify<V: HashBytes + ?Sized, H: TreeHasher>(
+    root_hash: &ObjTreeHash<H::Digest>,
+    obj: &V,
+    proof: &InclusionProof<H>,
+) -> bool {
+    let mut hash = H::hash_node(&H::hash_obj(obj), &proof.children);
+    for step in &proof.steps {
+        if step.index > step.siblings.len() {
+            return false;
+        }
+        let mut children = step.siblings.clone();
+        children.insert(step.index, hash);
+        hash = H::hash_node(&step.obj_hash, &children);
+    }
+    hash == root_hash.0
+}
+
+fn write_digests<D: Codec, W: Write>(digests: &[D], w: &mut W) -> Result<(), FormatError> {
+    digests.len().encode(w)?;
+    for d in digests {
+        d.encode(w)?;
+    }
+    Ok(())
+}
+
+fn read_digests<D: Codec, R: Read>(r: &mut R) -> Result<Vec<D>, FormatError> {
+    let count = usize::decode(r)?;
+    // No capacity up front, a corrupt count only costs the bytes actually there
+    let mut digests = Vec::new();
+    for _ in 0..count {
+        digests.push(D::decode(r)?);
+    }
+    Ok(digests)
+}
+
+impl<H: TreeHasher> InclusionProof<H> {
+    ///
+    /// The child indices from the root down to the proven object.
+    ///
+    pub fn path(&self) -> Vec<usize> {
+        self.steps.iter().rev().map(|s| s.index).collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        H::Digest: Codec,
+    {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("writing to a Vec cannot fail");
+        out
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> Result<(), FormatError>
+    where
+        H::Digest: Codec,
+    {
+        let mut body = Checksummed::new(&mut *w);
+        body.write_all(&PROOF_MAGIC)?;
+        PROOF_FORMAT_VERSION.encode(&mut body)?;
+        write_digests(&self.children, &mut body)?;
+        self.steps.len().encode(&mut body)?;
+        for step in &self.steps {
+            step.obj_hash.encode(&mut body)?;
+            step.index.encode(&mut body)?;
+            write_digests(&step.siblings, &mut body)?;
+        }
+        let hash = body.hash();
+        hash.encode(w)?;
+        Ok(())
+    }
+
+    ///
+    /// Reads a proof written by `to_bytes`. Only the framing is checked here, the
+    /// hashes are checked by `verify`.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError>
+    where
+        H::Digest: Codec,
+    {
+        let mut r = bytes;
+        let mut body = Checksummed::new(&mut r);
+        let mut magic = [0u8; 4];
+        body.read_exact(&mut magic)?;
+        if magic != PROOF_MAGIC {
+            return Err(FormatError::BadMagic {
+                found: magic,
+                expected: PROOF_MAGIC,
+            });
+        }
+        let version = u16::decode(&mut body)?;
+        if version != PROOF_FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion {
+                found: version,
+                expected: PROOF_FORMAT_VERSION,
+            });
+        }
+        let children = read_digests(&mut body)?;
+        let count = usize::decode(&mut body)?;
+        let mut steps = Vec::new();
+        for _ in 0..count {
+            steps.push(ProofStep {
+                obj_hash: H::Digest::decode(&mut body)?,
+                index: usize::decode(&mut body)?,
+                siblings: read_digests(&mut body)?,
+            });
+        }
+
+        let found = body.hash();
+        let expected = u32::decode(&mut r)?;
+        if expected != found {
+            return Err(FormatError::ChecksumMismatch { expected, found });
+        }
+        if !r.is_empty() {
+            return Err(FormatError::Corrupt("trailing bytes after proof"));
+        }
+        Ok(InclusionProof { children, steps })
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-OBJTREE+++++++++++++++++++++++++++++++++++++
+impl<V, H> ObjTree<V, H>
+where
+    V: Debug + Clone + HashBytes,
+    H: TreeHasher,
+{
+    ///
+    /// Proves the object at `path`, a child index per level from the root, against
+    /// the current root hash. None if there is no node at `path`.
+    ///
+    pub fn prove(&self, path: &[usize]) -> Option<InclusionProof<H>> {
+        let tree_hash = |id| self.get_tree_hash(id).map(|h| h.0.clone());
+        let target = self.node_at(path)?;
+        let children = self
+            .children(target)?
+            .iter()
+            .map(|c| tree_hash(*c))
+            .collect::<Option<Vec<_>>>()?;
+        let mut steps = Vec::with_capacity(path.len());
+        let mut id = target;
+        while let Some(parent) = self.parent(id) {
+            let mut siblings = Vec::new();
+            let mut index = 0;
+            for (i, c) in self.children(parent)?.iter().enumerate() {
+                if *c == id {
+                    index = i;
+                } else {
+                    siblings.push(tree_hash(*c)?);
+                }
+            }
+            steps.push(ProofStep {
+                obj_hash: self.get_obj_hash(parent)?.0.clone(),
+                index,
+                siblings,
+            });
+            id = parent;
+        }
+        Some(InclusionProof { children, steps })
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::merkle::WideHasher;
+
+// 1 has children 2, 3 and 4; 3 has children 5 and 6; 6 has a child 7
+#[cfg(test)]
+fn proof_sample<H: TreeHasher>() -> ObjTree<i32, H> {
+    let mut tree: ObjTree<i32, H> = ObjTree::with_hasher(1);
+    let root = tree.root();
+    for v in 2..5 {
+        tree.add(root, ObjTree::with_hasher(v)).unwrap();
+    }
+    let three = tree.node_at(&[1]).unwrap();
+    tree.add(three, ObjTree::with_hasher(5)).unwrap();
+    let six = tree.add(three, ObjTree::with_hasher(6)).unwrap();
+    tree.add(six, ObjTree::with_hasher(7)).unwrap();
+    tree
+}
+
+#[cfg(test)]
+fn proves_every_node<H: TreeHasher>() {
+    let tree = proof_sample::<H>();
+    for (id, obj) in tree.iter_pre_order() {
+        let path = tree.path_of(id).unwrap();
+        let proof = tree.prove(&path).unwrap();
+        assert_eq!(proof.path(), path);
+        assert!(verify(tree.root_hash(), obj, &proof));
+        // right place, wrong object
+        assert!(!verify(tree.root_hash(), &(obj + 100), &proof));
+    }
+}
+
+#[test]
+fn prove_and_verify() {
+    proves_every_node::<SipTreeHasher>();
+    proves_every_node::<WideHasher>();
+
+    let tree = proof_sample::<SipTreeHasher>();
+    assert!(tree.prove(&[]).unwrap().path().is_empty());
+    assert!(tree.prove(&[3]).is_none());
+    assert!(tree.prove(&[0, 0]).is_none());
+}
+
+#[test]
+fn tampered_proofs_fail() {
+    let mut tree = proof_sample::<SipTreeHasher>();
+    let proof = tree.prove(&[1, 1, 0]).unwrap();
+    assert!(verify(tree.root_hash(), &7, &proof));
+
+    let mut bad = proof.clone();
+    bad.steps[1].siblings[0] ^= 1;
+    assert!(!verify(tree.root_hash(), &7, &bad));
+
+    let mut bad = proof.clone();
+    bad.steps[1].index = 0;
+    assert!(!verify(tree.root_hash(), &7, &bad));
+    bad.steps[1].index = 9;
+    assert!(!verify(tree.root_hash(), &7, &bad));
+
+    let mut bad = proof.clone();
+    bad.steps[2].obj_hash ^= 1;
+    assert!(!verify(tree.root_hash(), &7, &bad));
+
+    let mut bad = proof.clone();
+    bad.steps.pop();
+    assert!(!verify(tree.root_hash(), &7, &bad));
+
+    let mut bad = proof.clone();
+    bad.children.push(0);
+    assert!(!verify(tree.root_hash(), &7, &bad));
+
+    // a proof goes stale once the tree changes
+    let old_root = *tree.root_hash();
+    let root = tree.root();
+    tree.add(root, ObjTree::new(8)).unwrap();
+    assert!(!verify(tree.root_hash(), &7, &proof));
+    assert!(verify(&old_root, &7, &proof));
+}
+
+#[test]
+fn proof_bytes_round_trip() {
+    let tree = proof_sample::<SipTreeHasher>();
+    let proof = tree.prove(&[1, 1]).unwrap();
+    let bytes = proof.to_bytes();
+    assert_eq!(&bytes[..4], b"GTPF");
+    let back: InclusionProof = InclusionProof::from_bytes(&bytes).unwrap();
+    assert_eq!(back, proof);
+    assert!(verify(tree.root_hash(), &6, &back));
+
+    let wide = proof_sample::<WideHasher>();
+    let proof = wide.prove(&[1, 1, 0]).unwrap();
+    let back = InclusionProof::<WideHasher>::from_bytes(&proof.to_bytes()).unwrap();
+    assert!(verify(wide.root_hash(), &7, &back));
+}
+
+#[test]
+fn proof_bytes_errors() {
+    let tree = proof_sample::<SipTreeHasher>();
+    let bytes = tree.prove(&[1, 1]).unwrap().to_bytes();
+
+    match InclusionProof::<SipTreeHasher>::from_bytes(&bytes[..bytes.len() - 1]) {
+        Err(FormatError::Truncated) => {}
+        other => panic!("expected Truncated, got {:?}", other),
+    }
+    let mut longer = bytes.clone();
+    longer.push(0);
+    match InclusionProof::<SipTreeHasher>::from_bytes(&longer) {
+        Err(FormatError::Corrupt(_)) => {}
+        other => panic!("expected Corrupt, got {:?}", other),
+    }
+    let mut version = bytes.clone();
+    version[4] = 1;
+    match InclusionProof::<SipTreeHasher>::from_bytes(&version) {
+        Err(FormatError::UnsupportedVersion {
+            found: 1,
+            expected: 2,
+        }) => {}
+        other => panic!("expected UnsupportedVersion, got {:?}", other),
+    }
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    match InclusionProof::<SipTreeHasher>::from_bytes(&magic) {
+        Err(FormatError::BadMagic { expected, .. }) => assert_eq!(&expected, b"GTPF"),
+        other => panic!("expected BadMagic, got {:?}", other),
+    }
+
+    // a flipped bit in a digest is caught by the checksum before verify sees it
+    let mut flipped = bytes.clone();
+    let last = flipped.len() - 5;
+    flipped[last] ^= 0x40;
+    match InclusionProof::<SipTreeHasher>::from_bytes(&flipped) {
+        Err(FormatError::ChecksumMismatch { .. }) => {}
+        other => panic!("expected ChecksumMismatch, got {:?}", other),
+    }
+}
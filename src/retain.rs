@@ -0,0 +1,312 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Conditional and bulk removal of values
+//
+//
+
+use std::fmt::Debug;
+use std::mem;
+use std::ops::RangeBounds;
+
+use super::BST::*;
+use super::{above_start, below_end};
+use super::{BSTInOrderIntoIterator, BSTNode, CursorMut, Direction, Summary, BST};
+
+// Removes the values failing `keep` from a search tree in which they form a run at
+// the `side` end. Kept nodes stay where they are; each cut node goes together
+// with its whole subtree on the `side`.
+fn cut_end<V, S, K>(tree: BST<V, S>, side: Direction, keep: K) -> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+    K: Fn(&V) -> bool,
+{
+    let mut path: Vec<Box<BSTNode<V, S>>> = Vec::new();
+    let mut current = tree;
+    loop {
+        let mut n = match current {
+            Empty => break,
+            NonEmpty(n) => n,
+        };
+        let kept = n.val.as_ref().is_none_or(&keep);
+        let (toward, away) = match side {
+            Direction::Left => (&mut n.left, &mut n.right),
+            Direction::Right => (&mut n.right, &mut n.left),
+        };
+        if kept {
+            current = mem::replace(toward, Empty);
+            path.push(n);
+        } else {
+            current = mem::replace(away, Empty);
+        }
+    }
+    while let Some(mut n) = path.pop() {
+        match side {
+            Direction::Left => n.left = current,
+            Direction::Right => n.right = current,
+        }
+        n.update_summary();
+        current = NonEmpty(n);
+    }
+    current
+}
+
+// Joins two trees where every value of `low` is less than every value of `high`
+fn concat<V, S>(low: BST<V, S>, mut high: BST<V, S>) -> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    if let Empty = low {
+        return high;
+    }
+    if let Empty = high {
+        return low;
+    }
+    let mut node = BSTNode {
+        val: high.take_min(),
+        left: low,
+        right: high,
+        depth: 1,
+        summary: S::identity(),
+    };
+    node.update_summary();
+    NonEmpty(Box::new(node))
+}
+
+///
+/// Iterator returned by `BST::extract_if`, removing and yielding the matching
+/// values in order.
+///
+/// Values are only examined as the iterator advances, so dropping it early
+/// leaves the rest of the tree untouched.
+///
+pub struct ExtractIf<'a, V: 'a, S: 'a, F>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+    F: FnMut(&V) -> bool,
+{
+    cursor: Option<CursorMut<'a, V, S>>,
+    pred: F,
+}
+
+impl<'a, V, S, F> Iterator for ExtractIf<'a, V, S, F>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+    F: FnMut(&V) -> bool,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        let cursor = self.cursor.as_mut()?;
+        // Past the last value the cursor rests on the ghost position
+        while let Some(&val) = cursor.current() {
+            if (self.pred)(&val) {
+                return cursor.remove_current();
+            }
+            cursor.move_next();
+        }
+        None
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Keeps only the values for which `f` returns true, visiting them in order.
+    ///
+    /// Values are removed where they are, so the rest of the tree keeps its shape.
+    ///
+    pub fn retain<F: FnMut(&V) -> bool>(&mut self, mut f: F) {
+        self.extract_if(|v| !f(v)).for_each(drop);
+    }
+
+    ///
+    /// Empties the tree, returning its values in order.
+    ///
+    pub fn drain(&mut self) -> BSTInOrderIntoIterator<V, S> {
+        mem::replace(self, Empty).into_iter()
+    }
+
+    ///
+    /// Returns an iterator that removes and yields, in order, the values for which
+    /// `pred` returns true. See `ExtractIf`.
+    ///
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, V, S, F>
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let cursor = match self.min_value() {
+            Some(first) => Some(self.lower_bound_cursor_mut(&first)),
+            None => None,
+        };
+        ExtractIf { cursor, pred }
+    }
+
+    ///
+    /// Removes every value in `range`.
+    ///
+    /// The values in range all hang below the highest one of them, so after one
+    /// search the run is cut away from both sides of that node and what is left
+    /// is concatenated back together. Takes O(h + k) for k removed values on a
+    /// tree of height h.
+    ///
+    pub fn remove_range<R: RangeBounds<V>>(&mut self, range: R) {
+        let (path, found) = self.unzip(|n| match n.val {
+            Some(ref v) if !above_start(&range, v) => Some(Direction::Right),
+            Some(ref v) if !below_end(&range, v) => Some(Direction::Left),
+            // Inside the range, or a node without a value blocks the search
+            _ => None,
+        });
+        match found {
+            NonEmpty(mut n) if n.val.is_some() => {
+                let low = cut_end(mem::replace(&mut n.left, Empty), Direction::Right, |v| {
+                    !above_start(&range, v)
+                });
+                let high = cut_end(mem::replace(&mut n.right, Empty), Direction::Left, |v| {
+                    !below_end(&range, v)
+                });
+                self.zip(path, concat(low, high), true);
+            }
+            rest => self.zip(path, rest, false),
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Sum};
+#[cfg(test)]
+use std::ops::Bound;
+
+#[cfg(test)]
+fn in_order(tree: &BST<i32, Sum<i32>>) -> Vec<i32> {
+    tree.iter_in_order().cloned().collect()
+}
+
+#[test]
+fn retain_test() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let mut seen = Vec::new();
+    tree.retain(|v| {
+        seen.push(*v);
+        v % 2 == 0
+    });
+    assert_eq!(seen, vec![1, 2, 6, 7, 8, 9, 10, 11, 13, 20]);
+    assert_eq!(in_order(&tree), vec![2, 6, 8, 10, 20]);
+    assert_eq!(tree.summary(), Sum(46));
+    assert_eq!(tree.validate(), Ok(()));
+
+    // keeping everything leaves the shape alone
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let pre: Vec<i32> = tree.iter_pre_order().cloned().collect();
+    tree.retain(|_| true);
+    assert_eq!(tree.iter_pre_order().cloned().collect::<Vec<i32>>(), pre);
+
+    tree.retain(|_| false);
+    assert_eq!(tree.size(), 0);
+    assert_eq!(tree.summary(), Sum(0));
+}
+
+#[test]
+fn drain_test() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let drained: Vec<i32> = tree.drain().collect();
+    assert_eq!(drained, vec![1, 2, 6, 7, 8, 9, 10, 11, 13, 20]);
+    assert_eq!(tree.size(), 0);
+    assert_eq!(tree.summary(), Sum(0));
+    assert_eq!(tree.drain().next(), None);
+}
+
+#[test]
+fn extract_if_is_lazy() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    let taken: Vec<i32> = tree.extract_if(|v| *v > 6).take(3).collect();
+    assert_eq!(taken, vec![7, 8, 9]);
+    assert_eq!(in_order(&tree), vec![1, 2, 6, 10, 11, 13, 20]);
+    assert_eq!(tree.summary(), Sum(63));
+    assert_eq!(tree.validate(), Ok(()));
+
+    let rest: Vec<i32> = tree.extract_if(|v| *v > 6).collect();
+    assert_eq!(rest, vec![10, 11, 13, 20]);
+    assert_eq!(in_order(&tree), vec![1, 2, 6]);
+
+    let mut empty: BST<i32> = BST::new();
+    assert_eq!(empty.extract_if(|_| true).next(), None);
+}
+
+#[test]
+fn remove_range_test() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    tree.remove_range(7..11);
+    assert_eq!(in_order(&tree), vec![1, 2, 6, 11, 13, 20]);
+    assert_eq!(tree.summary(), Sum(53));
+    assert_eq!(tree.aggregate(2..=13), Sum(32));
+    assert_eq!(tree.validate(), Ok(()));
+
+    tree.remove_range(..=2);
+    assert_eq!(in_order(&tree), vec![6, 11, 13, 20]);
+    tree.remove_range(12..);
+    assert_eq!(in_order(&tree), vec![6, 11]);
+    // nothing in range
+    tree.remove_range(7..11);
+    tree.remove_range((Bound::Included(9), Bound::Excluded(3)));
+    assert_eq!(in_order(&tree), vec![6, 11]);
+    assert_eq!(tree.summary(), Sum(17));
+
+    tree.remove_range(..);
+    assert_eq!(tree.size(), 0);
+}
+
+#[test]
+fn remove_range_keeps_unaffected_nodes() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    for v in &[9, 11, 2] {
+        tree.insert(*v);
+    }
+    // 9..=11 hangs under 10, away from the root
+    tree.remove_range(9..12);
+    assert_eq!(
+        tree.iter_pre_order().cloned().collect::<Vec<i32>>(),
+        vec![8, 6, 1, 2, 7, 13, 20]
+    );
+}
+
+#[test]
+fn remove_range_deep_chain() {
+    let mut tree: BST<u32, Sum<u32>> = BST::new();
+    for v in 0..5000 {
+        tree.insert(v);
+    }
+    tree.remove_range(1000..4000);
+    assert_eq!(tree.size(), 2000);
+    assert_eq!(tree.height(), 2000);
+    assert_eq!(
+        tree.summary(),
+        Sum((0..1000).sum::<u32>() + (4000..5000).sum::<u32>())
+    );
+    tree.retain(|v| v % 2 == 1);
+    assert_eq!(tree.size(), 1000);
+    assert_eq!(tree.validate(), Ok(()));
+}
@@ -0,0 +1,195 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Order preserving rotations for building balancing schemes on top of BST
+//
+//
+
+use std::fmt::Debug;
+use std::mem;
+
+use super::toward;
+use super::BST::*;
+use super::{Direction, Summary, BST};
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Rotates the root left: its right child becomes the new root and the old root
+    /// its left child. The in-order sequence is unchanged.
+    ///
+    /// Returns false, leaving the tree alone, if there is no right child.
+    ///
+    pub fn rotate_left(&mut self) -> bool {
+        self.rotate(Direction::Left)
+    }
+
+    ///
+    /// Rotates the root right: its left child becomes the new root and the old root
+    /// its right child. The in-order sequence is unchanged.
+    ///
+    /// Returns false, leaving the tree alone, if there is no left child.
+    ///
+    pub fn rotate_right(&mut self) -> bool {
+        self.rotate(Direction::Right)
+    }
+
+    ///
+    /// Rotates the subtree rooted at the node holding `val` to the left.
+    ///
+    /// Returns false if `val` is not found or the node has no right child.
+    ///
+    pub fn rotate_left_at(&mut self, val: V) -> bool {
+        self.rotate_at(val, Direction::Left)
+    }
+
+    ///
+    /// Rotates the subtree rooted at the node holding `val` to the right.
+    ///
+    /// Returns false if `val` is not found or the node has no left child.
+    ///
+    pub fn rotate_right_at(&mut self, val: V) -> bool {
+        self.rotate_at(val, Direction::Right)
+    }
+
+    // Finds `val` by search and rotates there, refreshing the summaries above
+    fn rotate_at(&mut self, val: V, dir: Direction) -> bool {
+        let (path, mut found) = self.unzip(|n| toward(n, &val));
+        let at_val = match found {
+            NonEmpty(ref n) => n.val == Some(val),
+            Empty => false,
+        };
+        let rotated = at_val && found.rotate(dir);
+        self.zip(path, found, rotated);
+        rotated
+    }
+
+    fn rotate(&mut self, dir: Direction) -> bool {
+        let mut root = match mem::replace(self, Empty) {
+            Empty => return false,
+            NonEmpty(n) => n,
+        };
+        // The child that moves up, on the side opposite to the rotation
+        let pivot = match dir {
+            Direction::Left => mem::replace(&mut root.right, Empty),
+            Direction::Right => mem::replace(&mut root.left, Empty),
+        };
+        let mut pivot = match pivot {
+            Empty => {
+                *self = NonEmpty(root);
+                return false;
+            }
+            NonEmpty(p) => p,
+        };
+        // The pivot's inner subtree changes parents, then the old root moves down
+        match dir {
+            Direction::Left => {
+                root.right = mem::replace(&mut pivot.left, Empty);
+                root.update_summary();
+                pivot.left = NonEmpty(root);
+            }
+            Direction::Right => {
+                root.left = mem::replace(&mut pivot.right, Empty);
+                root.update_summary();
+                pivot.right = NonEmpty(root);
+            }
+        }
+        pivot.update_summary();
+        *self = NonEmpty(pivot);
+        true
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Sum};
+
+#[cfg(test)]
+fn pre_order(tree: &BST<i32, Sum<i32>>) -> Vec<i32> {
+    tree.iter_pre_order().cloned().collect()
+}
+
+#[test]
+fn rotate_root_both_ways() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    assert!(tree.rotate_left());
+    assert_eq!(pre_order(&tree), vec![13, 8, 6, 1, 7, 10, 20]);
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.summary(), Sum(65));
+    if let NonEmpty(ref n) = tree {
+        assert_eq!(n.left.summary(), Sum(32));
+    }
+
+    // rotating back restores the original shape
+    assert!(tree.rotate_right());
+    assert_eq!(pre_order(&tree), vec![8, 6, 1, 7, 13, 10, 20]);
+    assert_eq!(tree.aggregate(6..11), Sum(31));
+}
+
+#[test]
+fn rotate_at_inner_nodes() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    assert!(tree.rotate_right_at(13));
+    assert_eq!(pre_order(&tree), vec![8, 6, 1, 7, 10, 13, 20]);
+    assert!(tree.rotate_left_at(6));
+    assert_eq!(pre_order(&tree), vec![8, 7, 6, 1, 10, 13, 20]);
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.aggregate(9..), Sum(43));
+    assert_eq!(tree.aggregate(..8), Sum(14));
+}
+
+#[test]
+fn rotate_without_pivot_is_a_no_op() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    assert!(!tree.rotate_left_at(20));
+    assert!(!tree.rotate_right_at(1));
+    assert!(!tree.rotate_left_at(99));
+    assert_eq!(pre_order(&tree), vec![8, 6, 1, 7, 13, 10, 20]);
+
+    let mut empty: BST<i32> = BST::new();
+    assert!(!empty.rotate_left());
+    assert!(!empty.rotate_right());
+}
+
+#[test]
+fn rotations_balance_a_chain() {
+    let mut tree: BST<u32> = BST::new();
+    for v in 1..8 {
+        tree.insert(v);
+    }
+    assert_eq!(tree.height(), 7);
+    // 1..7 leans right; three root rotations bring 4 up, then each side needs one more
+    assert!(tree.rotate_left());
+    assert!(tree.rotate_left());
+    assert!(tree.rotate_left());
+    assert!(tree.rotate_right_at(3));
+    assert!(tree.rotate_left_at(5));
+    assert_eq!(
+        tree.iter_pre_order().cloned().collect::<Vec<u32>>(),
+        vec![4, 2, 1, 3, 6, 5, 7]
+    );
+    assert_eq!(tree.height(), 3);
+}
+
+#[test]
+fn rotate_at_deep_chain() {
+    // Built by hand, inserting sorted values is quadratic
+    let mut tree: BST<u64, Sum<u64>> = BST::new();
+    for v in (0..100_000u64).rev() {
+        let mut node = BST::leaf(v);
+        if let NonEmpty(ref mut n) = node {
+            n.right = tree;
+            n.update_summary();
+        }
+        tree = node;
+    }
+    assert!(tree.rotate_left_at(99_998));
+    assert!(!tree.rotate_left_at(99_999));
+    assert!(!tree.rotate_right_at(100_000));
+    assert_eq!(tree.summary(), Sum((0..100_000).sum()));
+    assert_eq!(tree.aggregate(99_990..), Sum((99_990..100_000).sum()));
+    assert_eq!(tree.max_value(), Some(99_999));
+}
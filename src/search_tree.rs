@@ -0,0 +1,277 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Traits shared by the search trees, so code can be written once against them
+//
+//
+
+use std::fmt::Debug;
+use std::ops::RangeBounds;
+
+use super::BST::*;
+use super::{above_start, below_end, extreme};
+use super::{BSTInOrderIter, Direction, Summary, BST};
+
+///
+/// An ordered set of values kept in a search tree.
+///
+/// Generic code and test suites can use this instead of a concrete tree, so any
+/// balanced variant implementing it can be dropped in.
+///
+pub trait SearchTree<V> {
+    ///
+    /// Adds `val`, returning false if it was already present.
+    ///
+    fn insert(&mut self, val: V) -> bool;
+
+    ///
+    /// Removes `val`, returning false if it was not present.
+    ///
+    fn remove(&mut self, val: &V) -> bool;
+
+    fn get(&self, val: &V) -> Option<&V>;
+
+    fn contains(&self, val: &V) -> bool {
+        self.get(val).is_some()
+    }
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn min(&self) -> Option<&V>;
+
+    fn max(&self) -> Option<&V>;
+
+    ///
+    /// All values in increasing order.
+    ///
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a;
+
+    ///
+    /// The values inside `range` in increasing order.
+    ///
+    fn range<'a, R>(&'a self, range: R) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a,
+        R: RangeBounds<V> + 'a;
+}
+
+///
+/// Read-only navigation of a binary tree, one node at a time.
+///
+/// A node without a child on some side returns None for it.
+///
+pub trait BinaryTreeNode<V> {
+    fn value(&self) -> Option<&V>;
+
+    fn left(&self) -> Option<&Self>;
+
+    fn right(&self) -> Option<&Self>;
+
+    fn child(&self, side: Direction) -> Option<&Self> {
+        match side {
+            Direction::Left => self.left(),
+            Direction::Right => self.right(),
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.left().is_none() && self.right().is_none()
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> SearchTree<V> for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn insert(&mut self, val: V) -> bool {
+        BST::insert(self, val)
+    }
+
+    fn remove(&mut self, val: &V) -> bool {
+        BST::remove(self, *val)
+    }
+
+    fn get(&self, val: &V) -> Option<&V> {
+        self.subtree_at(val).and_then(BST::peek)
+    }
+
+    fn len(&self) -> usize {
+        self.size()
+    }
+
+    fn min(&self) -> Option<&V> {
+        extreme(self, Direction::Left)
+    }
+
+    fn max(&self) -> Option<&V> {
+        extreme(self, Direction::Right)
+    }
+
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a,
+    {
+        Box::new(self.iter_in_order())
+    }
+
+    // Starts an in-order walk at the first value in range and stops after the last
+    fn range<'a, R>(&'a self, range: R) -> Box<dyn Iterator<Item = &'a V> + 'a>
+    where
+        V: 'a,
+        R: RangeBounds<V> + 'a,
+    {
+        let mut iter = BSTInOrderIter {
+            iter_stack: Vec::new(),
+        };
+        let mut tree = self;
+        while let NonEmpty(ref node) = *tree {
+            match node.val {
+                Some(ref v) if !above_start(&range, v) => tree = &node.right,
+                _ => {
+                    iter.iter_stack.push(node);
+                    tree = &node.left;
+                }
+            }
+        }
+        Box::new(iter.take_while(move |v| below_end(&range, *v)))
+    }
+}
+
+impl<V, S> BinaryTreeNode<V> for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    fn value(&self) -> Option<&V> {
+        self.peek()
+    }
+
+    fn left(&self) -> Option<&Self> {
+        match *self {
+            NonEmpty(ref n) => match n.left {
+                Empty => None,
+                ref left => Some(left),
+            },
+            Empty => None,
+        }
+    }
+
+    fn right(&self) -> Option<&Self> {
+        match *self {
+            NonEmpty(ref n) => match n.right {
+                Empty => None,
+                ref right => Some(right),
+            },
+            Empty => None,
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Count, PersistentBST, SAMPLE};
+
+// Runs against any SearchTree, so future trees can reuse it
+#[cfg(test)]
+fn exercise_search_tree<T: SearchTree<i32> + Default>() {
+    let mut tree = T::default();
+    assert!(tree.is_empty());
+    assert_eq!(tree.min(), None);
+    assert_eq!(tree.iter().next(), None);
+
+    for v in SAMPLE.iter().chain(&[9, 11, 2]) {
+        assert!(tree.insert(*v));
+    }
+    assert!(!tree.insert(10));
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.get(&9), Some(&9));
+    assert_eq!(tree.get(&5), None);
+    assert!(tree.contains(&20));
+    assert_eq!(tree.min(), Some(&1));
+    assert_eq!(tree.max(), Some(&20));
+    assert_eq!(
+        tree.iter().cloned().collect::<Vec<i32>>(),
+        vec![1, 2, 6, 7, 8, 9, 10, 11, 13, 20]
+    );
+    assert_eq!(
+        tree.range(7..11).cloned().collect::<Vec<i32>>(),
+        vec![7, 8, 9, 10]
+    );
+    assert_eq!(
+        tree.range(3..=13).cloned().collect::<Vec<i32>>(),
+        vec![6, 7, 8, 9, 10, 11, 13]
+    );
+    assert_eq!(tree.range(..2).cloned().collect::<Vec<i32>>(), vec![1]);
+    assert_eq!(tree.range(14..).cloned().collect::<Vec<i32>>(), vec![20]);
+    assert_eq!(tree.range(21..).next(), None);
+
+    assert!(tree.remove(&8));
+    assert!(!tree.remove(&8));
+    assert!(tree.remove(&1));
+    assert_eq!(tree.len(), 8);
+    assert_eq!(tree.min(), Some(&2));
+    assert_eq!(
+        tree.iter().cloned().collect::<Vec<i32>>(),
+        vec![2, 6, 7, 9, 10, 11, 13, 20]
+    );
+}
+
+// Counts leaves through navigation only
+#[cfg(test)]
+fn leaves<V, T: BinaryTreeNode<V>>(root: &T) -> usize {
+    let mut stack = vec![root];
+    let mut count = 0;
+    while let Some(node) = stack.pop() {
+        if node.value().is_some() && node.is_leaf() {
+            count += 1;
+        }
+        stack.extend(node.left());
+        stack.extend(node.right());
+    }
+    count
+}
+
+#[test]
+fn bst_is_a_search_tree() {
+    exercise_search_tree::<BST<i32>>();
+    exercise_search_tree::<BST<i32, Count>>();
+}
+
+#[test]
+fn persistent_is_a_search_tree() {
+    exercise_search_tree::<PersistentBST<i32>>();
+
+    // Updates through the trait leave earlier versions alone
+    let mut tree: PersistentBST<i32> = vec![1, 2, 3].into_iter().collect();
+    let before = tree.clone();
+    assert!(SearchTree::insert(&mut tree, 4));
+    assert!(SearchTree::remove(&mut tree, &1));
+    assert_eq!(before.iter().cloned().collect::<Vec<i32>>(), vec![1, 2, 3]);
+    assert_eq!(tree.iter().cloned().collect::<Vec<i32>>(), vec![2, 3, 4]);
+}
+
+#[test]
+fn bst_node_navigation() {
+    let tree: BST<i32> = sample_tree();
+    assert_eq!(tree.value(), Some(&8));
+    let six = tree.left().unwrap();
+    assert_eq!(six.value(), Some(&6));
+    assert_eq!(
+        six.child(Direction::Right).and_then(|n| n.value()),
+        Some(&7)
+    );
+    assert!(six.left().unwrap().is_leaf());
+    assert!(!tree.is_leaf());
+    assert_eq!(leaves(&tree), 4);
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.value(), None);
+    assert_eq!(leaves(&empty), 0);
+}
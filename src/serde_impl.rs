@@ -0,0 +1,400 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Serde support, enabled with the `serde` cargo feature
+//
+//
+
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::BST::*;
+use super::{BSTNode, Interval, IntervalTree, PersistentBST, Summary, BST};
+
+//++++++++++++++++++++++++++++++++++Sorted sequence+++++++++++++++++++++++++++++++++++++
+
+///
+/// A BST is written as the sorted sequence of its values and read back as a
+/// balanced tree, whatever shape it had when it was written.
+///
+impl<V, S> Serialize for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+    S: Summary<V>,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter_in_order())
+    }
+}
+
+impl<'de, V, S> Deserialize<'de> for BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+    S: Summary<V>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values: Vec<V> = Vec::deserialize(deserializer)?;
+        Ok(values.into_iter().collect())
+    }
+}
+
+impl<V> Serialize for PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter_in_order())
+    }
+}
+
+impl<'de, V> Deserialize<'de> for PersistentBST<V>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values: Vec<V> = Vec::deserialize(deserializer)?;
+        Ok(values.into_iter().collect())
+    }
+}
+
+///
+/// An Interval is written as a `(start, end)` pair.
+///
+impl<T> Serialize for Interval<T>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        (self.start(), self.end()).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Interval<T>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (start, end) = <(T, T)>::deserialize(deserializer)?;
+        Interval::try_new(start, end).ok_or_else(|| {
+            D::Error::custom(format!(
+                "interval start {:?} is after end {:?}",
+                start, end
+            ))
+        })
+    }
+}
+
+///
+/// An IntervalTree is written as its `(interval, value)` pairs in order.
+///
+impl<T, V> Serialize for IntervalTree<T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+    V: Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T, V> Deserialize<'de> for IntervalTree<T, V>
+where
+    T: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries: Vec<(Interval<T>, V)> = Vec::deserialize(deserializer)?;
+        let mut tree = IntervalTree::new();
+        for (interval, value) in entries {
+            tree.insert(interval, value);
+        }
+        Ok(tree)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++Shape preserving+++++++++++++++++++++++++++++++++++++
+
+const HAS_LEFT: u8 = 1;
+const HAS_RIGHT: u8 = 2;
+
+///
+/// Wraps a BST so it is written with its exact node layout.
+///
+/// The tree is encoded as a flat pre-order sequence of `(value, children)` pairs,
+/// where `children` has bit 0 set for a left child and bit 1 for a right child.
+/// Reading it back rebuilds the very same shape, even one whose ordering was broken
+/// by `swap_left`/`swap_right`. The encoding is flat, so arbitrarily deep trees do
+/// not hit the recursion limits of formats like JSON.
+///
+pub struct ShapePreserving<T>(pub T);
+
+impl<V, S> Serialize for ShapePreserving<BST<V, S>>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+    S: Summary<V>,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        shape_preserving::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, V, S> Deserialize<'de> for ShapePreserving<BST<V, S>>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+    S: Summary<V>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        shape_preserving::deserialize(deserializer).map(ShapePreserving)
+    }
+}
+
+///
+/// Shape preserving encoding for use with `#[serde(with = "shape_preserving")]`.
+///
+pub mod shape_preserving {
+    use super::*;
+
+    pub fn serialize<V, S, Ser>(tree: &BST<V, S>, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        V: Debug + Copy + Clone + Ord + PartialEq + Serialize,
+        S: Summary<V>,
+        Ser: Serializer,
+    {
+        serializer.collect_seq(PreOrderShape {
+            iter_stack: match *tree {
+                Empty => Vec::new(),
+                NonEmpty(ref node) => vec![&**node],
+            },
+        })
+    }
+
+    pub fn deserialize<'de, V, S, D>(deserializer: D) -> Result<BST<V, S>, D::Error>
+    where
+        V: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+        S: Summary<V>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(ShapeVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+// Pre-order walk yielding each value with its child flags
+struct PreOrderShape<'a, V: 'a, S: 'a> {
+    iter_stack: Vec<&'a BSTNode<V, S>>,
+}
+
+impl<'a, V, S> Iterator for PreOrderShape<'a, V, S> {
+    type Item = (&'a Option<V>, u8);
+
+    fn next(&mut self) -> Option<(&'a Option<V>, u8)> {
+        let node = self.iter_stack.pop()?;
+        let mut flags = 0;
+        if let NonEmpty(ref r) = node.right {
+            flags |= HAS_RIGHT;
+            self.iter_stack.push(r);
+        }
+        if let NonEmpty(ref l) = node.left {
+            flags |= HAS_LEFT;
+            self.iter_stack.push(l);
+        }
+        Some((&node.val, flags))
+    }
+}
+
+// A node still waiting for the subtrees its flags announced
+struct Frame<V, S> {
+    node: Box<BSTNode<V, S>>,
+    needs_left: bool,
+    needs_right: bool,
+}
+
+struct ShapeVisitor<V, S> {
+    marker: PhantomData<(V, S)>,
+}
+
+impl<'de, V, S> Visitor<'de> for ShapeVisitor<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq + Deserialize<'de>,
+    S: Summary<V>,
+{
+    type Value = BST<V, S>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a pre-order sequence of (value, children) pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BST<V, S>, A::Error> {
+        let mut stack: Vec<Frame<V, S>> = Vec::new();
+        let mut root: Option<BST<V, S>> = None;
+
+        while let Some((val, flags)) = seq.next_element::<(Option<V>, u8)>()? {
+            if root.is_some() {
+                return Err(A::Error::custom("values after the end of the tree"));
+            }
+            if flags & !(HAS_LEFT | HAS_RIGHT) != 0 {
+                return Err(A::Error::custom(format!("invalid child flags {}", flags)));
+            }
+            stack.push(Frame {
+                node: Box::new(BSTNode {
+                    val,
+                    left: Empty,
+                    right: Empty,
+                    depth: 1,
+                    summary: S::identity(),
+                }),
+                needs_left: flags & HAS_LEFT != 0,
+                needs_right: flags & HAS_RIGHT != 0,
+            });
+
+            // Hang every finished subtree on its parent
+            while stack
+                .last()
+                .is_some_and(|f| !f.needs_left && !f.needs_right)
+            {
+                let mut done = match stack.pop() {
+                    Some(frame) => frame.node,
+                    None => break,
+                };
+                done.update_summary();
+                match stack.last_mut() {
+                    None => root = Some(NonEmpty(done)),
+                    Some(parent) => {
+                        if parent.needs_left {
+                            parent.node.left = NonEmpty(done);
+                            parent.needs_left = false;
+                        } else {
+                            parent.node.right = NonEmpty(done);
+                            parent.needs_right = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        match root {
+            Some(tree) => Ok(tree),
+            None if stack.is_empty() => Ok(Empty),
+            None => Err(A::Error::custom("tree ends before all children were read")),
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use serde_json;
+#[cfg(test)]
+use super::{sample_tree, Sum};
+
+#[test]
+fn serde_sorted_round_trip_rebalances() {
+    let mut tree: BST<i32> = BST::new();
+    for v in 0..15 {
+        tree.insert(v);
+    }
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, "[0,1,2,3,4,5,6,7,8,9,10,11,12,13,14]");
+
+    let loaded: BST<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        loaded.iter_in_order().cloned().collect::<Vec<i32>>(),
+        (0..15).collect::<Vec<i32>>()
+    );
+    assert_eq!(loaded.height(), 4);
+}
+
+#[test]
+fn serde_sorted_accepts_unsorted_input() {
+    let loaded: BST<i32, Sum<i32>> = serde_json::from_str("[5, 3, 9, 3, 1]").unwrap();
+    assert_eq!(loaded.iter_in_order().cloned().collect::<Vec<i32>>(), vec![1, 3, 5, 9]);
+    assert_eq!(loaded.summary(), Sum(18));
+}
+
+#[test]
+fn serde_shape_round_trip() {
+    let mut tree: BST<i32> = sample_tree();
+    // an out of order layout must survive the round trip too
+    tree.swap_right();
+
+    let json = serde_json::to_string(&ShapePreserving(tree)).unwrap();
+    assert_eq!(json, "[[13,3],[6,3],[1,0],[7,0],[8,3],[10,0],[20,0]]");
+
+    let ShapePreserving(loaded): ShapePreserving<BST<i32>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        loaded.iter_pre_order().cloned().collect::<Vec<i32>>(),
+        vec![13, 6, 1, 7, 8, 10, 20]
+    );
+    assert_eq!(
+        loaded.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![1, 6, 7, 13, 10, 8, 20]
+    );
+}
+
+#[test]
+fn serde_shape_deep_tree() {
+    let mut tree: BST<u32, Sum<u32>> = BST::new();
+    for v in 0..1000 {
+        tree.insert(v);
+    }
+    let json = serde_json::to_string(&ShapePreserving(tree)).unwrap();
+    let ShapePreserving(loaded): ShapePreserving<BST<u32, Sum<u32>>> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.height(), 1000);
+    assert_eq!(loaded.summary(), Sum(499_500));
+}
+
+#[test]
+fn serde_shape_empty_and_malformed() {
+    let ShapePreserving(empty): ShapePreserving<BST<i32>> = serde_json::from_str("[]").unwrap();
+    assert_eq!(empty.size(), 0);
+
+    // root announces a left child that never comes
+    assert!(serde_json::from_str::<ShapePreserving<BST<i32>>>("[[8,1]]").is_err());
+    // a second root
+    assert!(serde_json::from_str::<ShapePreserving<BST<i32>>>("[[8,0],[9,0]]").is_err());
+    // unknown flag bits
+    assert!(serde_json::from_str::<ShapePreserving<BST<i32>>>("[[8,4]]").is_err());
+}
+
+#[test]
+fn serde_shape_with_attribute_functions() {
+    let tree: BST<i32> = sample_tree();
+    let mut out = Vec::new();
+    shape_preserving::serialize(&tree, &mut serde_json::Serializer::new(&mut out)).unwrap();
+    let loaded: BST<i32> =
+        shape_preserving::deserialize(&mut serde_json::Deserializer::from_slice(&out)).unwrap();
+    assert_eq!(
+        loaded.iter_pre_order().cloned().collect::<Vec<i32>>(),
+        tree.iter_pre_order().cloned().collect::<Vec<i32>>()
+    );
+}
+
+#[test]
+fn serde_persistent_round_trip() {
+    let tree: PersistentBST<i32> = vec![8, 13, 6, 1].into_iter().collect();
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, "[1,6,8,13]");
+    let loaded: PersistentBST<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.size(), 4);
+    assert!(loaded.contains(13));
+}
+
+#[test]
+fn serde_interval_tree_round_trip() {
+    let mut tree = IntervalTree::new();
+    tree.insert(Interval::new(5, 10), "a".to_string());
+    tree.insert(Interval::new(1, 3), "b".to_string());
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, r#"[[[1,3],"b"],[[5,10],"a"]]"#);
+
+    let loaded: IntervalTree<i32, String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded.get(&Interval::new(5, 10)), Some(&"a".to_string()));
+    assert_eq!(loaded.stabbing(2).count(), 1);
+
+    assert!(serde_json::from_str::<Interval<i32>>("[4,1]").is_err());
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Structural statistics for watching the health of a tree
+//
+//
+
+use std::cmp;
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Summary, BST};
+
+///
+/// Shape report produced by `BST::stats`.
+///
+/// Depths count nodes, as `height` and `min_depth` do, so the root is at depth 1.
+/// Path lengths count edges from the root, so the root has path length 0.
+/// `level_widths[i]` is the number of nodes at depth `i + 1`. The balance factor
+/// of a node is the height difference of its two subtrees, reported here as the
+/// largest absolute value found anywhere in the tree.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub internal: usize,
+    pub height: u64,
+    pub min_depth: u64,
+    pub avg_path_length: f64,
+    pub max_path_length: u64,
+    pub level_widths: Vec<usize>,
+    pub max_balance_factor: u64,
+    pub optimal_height: u64,
+}
+
+impl TreeStats {
+    ///
+    /// How many levels taller the tree is than a perfectly balanced one.
+    ///
+    pub fn excess_height(&self) -> u64 {
+        self.height - self.optimal_height
+    }
+}
+
+// Smallest height any binary tree with `nodes` nodes can have
+fn optimal_height(nodes: usize) -> u64 {
+    let mut height = 0;
+    let mut capacity: u128 = 0;
+    while capacity < nodes as u128 {
+        height += 1;
+        capacity = capacity * 2 + 1;
+    }
+    height
+}
+
+enum Visit<'a, V: 'a, S: 'a> {
+    Enter(&'a BSTNode<V, S>, u64),
+    Exit(&'a BSTNode<V, S>),
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Gathers the structural statistics of the tree in a single traversal.
+    ///
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            nodes: 0,
+            leaves: 0,
+            internal: 0,
+            height: 0,
+            min_depth: 0,
+            avg_path_length: 0.0,
+            max_path_length: 0,
+            level_widths: Vec::new(),
+            max_balance_factor: 0,
+            optimal_height: 0,
+        };
+        let mut total_path_length: u64 = 0;
+
+        // Post-order walk; subtree heights of finished children wait on `heights`
+        let mut heights: Vec<u64> = Vec::new();
+        let mut stack = match *self {
+            Empty => Vec::new(),
+            NonEmpty(ref n) => vec![Visit::Enter(&**n, 1)],
+        };
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(node, depth) => {
+                    stats.nodes += 1;
+                    total_path_length += depth - 1;
+                    if stats.level_widths.len() < depth as usize {
+                        stats.level_widths.push(0);
+                    }
+                    stats.level_widths[depth as usize - 1] += 1;
+
+                    stack.push(Visit::Exit(node));
+                    let mut leaf = true;
+                    for child in &[&node.right, &node.left] {
+                        if let NonEmpty(ref c) = **child {
+                            stack.push(Visit::Enter(&**c, depth + 1));
+                            leaf = false;
+                        }
+                    }
+                    if leaf {
+                        stats.leaves += 1;
+                        if stats.min_depth == 0 || depth < stats.min_depth {
+                            stats.min_depth = depth;
+                        }
+                    } else {
+                        stats.internal += 1;
+                    }
+                }
+                Visit::Exit(node) => {
+                    // The right child finished last, so its height is on top
+                    let right = match node.right {
+                        NonEmpty(_) => heights.pop().unwrap_or(0),
+                        Empty => 0,
+                    };
+                    let left = match node.left {
+                        NonEmpty(_) => heights.pop().unwrap_or(0),
+                        Empty => 0,
+                    };
+                    let balance = cmp::max(left, right) - cmp::min(left, right);
+                    stats.max_balance_factor = cmp::max(stats.max_balance_factor, balance);
+                    heights.push(cmp::max(left, right) + 1);
+                }
+            }
+        }
+
+        stats.height = heights.pop().unwrap_or(0);
+        stats.max_path_length = stats.height.saturating_sub(1);
+        stats.optimal_height = optimal_height(stats.nodes);
+        if stats.nodes > 0 {
+            stats.avg_path_length = total_path_length as f64 / stats.nodes as f64;
+        }
+        stats
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn stats_balanced_tree() {
+    let tree: BST<i32> = sample_tree();
+    let stats = tree.stats();
+    assert_eq!(stats.nodes, 7);
+    assert_eq!(stats.leaves, 4);
+    assert_eq!(stats.internal, 3);
+    assert_eq!(stats.height, 3);
+    assert_eq!(stats.min_depth, 3);
+    assert_eq!(stats.level_widths, vec![1, 2, 4]);
+    assert_eq!(stats.max_path_length, 2);
+    assert!((stats.avg_path_length - 10.0 / 7.0).abs() < 1e-9);
+    assert_eq!(stats.max_balance_factor, 0);
+    assert_eq!(stats.optimal_height, 3);
+    assert_eq!(stats.excess_height(), 0);
+}
+
+#[test]
+fn stats_match_recursive_queries() {
+    let mut tree: BST<i32> = BST::new();
+    for v in &[50, 30, 70, 20, 40, 60, 80, 10, 5, 65, 66, 67] {
+        tree.insert(*v);
+        let stats = tree.stats();
+        assert_eq!(stats.height, tree.height());
+        assert_eq!(stats.min_depth, tree.min_depth());
+        assert_eq!(stats.nodes, tree.size());
+        assert_eq!(stats.level_widths.iter().sum::<usize>(), stats.nodes);
+    }
+    let stats = tree.stats();
+    // 60 has only the chain 65 -> 66 -> 67 on its right
+    assert_eq!(stats.max_balance_factor, 3);
+    assert_eq!(stats.optimal_height, 4);
+    assert_eq!(stats.excess_height(), 2);
+}
+
+#[test]
+fn stats_chain_and_empty() {
+    let empty: BST<i32> = BST::new();
+    let stats = empty.stats();
+    assert_eq!(stats.nodes, 0);
+    assert_eq!(stats.height, 0);
+    assert_eq!(stats.avg_path_length, 0.0);
+    assert!(stats.level_widths.is_empty());
+
+    let mut chain: BST<u32> = BST::new();
+    for v in 0..100 {
+        chain.insert(v);
+    }
+    let stats = chain.stats();
+    assert_eq!(stats.height, 100);
+    assert_eq!(stats.min_depth, 100);
+    assert_eq!(stats.leaves, 1);
+    assert_eq!(stats.max_balance_factor, 99);
+    assert_eq!(stats.optimal_height, 7);
+    assert_eq!(stats.avg_path_length, 49.5);
+}
@@ -0,0 +1,154 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Inspecting, detaching and grafting whole subtrees
+//
+//
+
+use std::fmt::Debug;
+
+use super::toward;
+use super::BST::*;
+use super::{Direction, Summary, BST};
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Returns the subtree rooted at the node holding `val`.
+    ///
+    pub fn subtree_at(&self, val: &V) -> Option<&BST<V, S>> {
+        let mut tree = self;
+        while let NonEmpty(ref n) = *tree {
+            tree = match toward(n, val) {
+                None if n.val.as_ref() == Some(val) => return Some(tree),
+                None => return None,
+                Some(Direction::Left) => &n.left,
+                Some(Direction::Right) => &n.right,
+            };
+        }
+        None
+    }
+
+    ///
+    /// Removes the node holding `val` together with all of its descendants and
+    /// returns them as a tree of their own.
+    ///
+    pub fn detach_subtree(&mut self, val: &V) -> Option<BST<V, S>> {
+        let (path, found) = self.unzip(|n| toward(n, val));
+        let (detached, rest) = match found {
+            NonEmpty(ref n) if n.val.as_ref() == Some(val) => (Some(found), Empty),
+            other => (None, other),
+        };
+        self.zip(path, rest, detached.is_some());
+        detached
+    }
+
+    ///
+    /// Hangs `other` into the empty slot where its values belong.
+    ///
+    /// Succeeds only if `other` is a valid search tree and no node on the way down
+    /// falls inside its range of values, so ordering is kept. Otherwise `other` is
+    /// handed back untouched.
+    ///
+    pub fn graft(&mut self, other: BST<V, S>) -> Result<(), BST<V, S>> {
+        let (lo, hi) = match (other.min_value(), other.max_value()) {
+            (Some(lo), Some(hi)) => (lo, hi),
+            // Nothing to graft
+            _ => return Ok(()),
+        };
+        if other.validate().is_err() {
+            return Err(other);
+        }
+        let (path, slot) = self.unzip(|n| match n.val {
+            Some(v) if hi < v => Some(Direction::Left),
+            Some(v) if lo > v => Some(Direction::Right),
+            _ => None,
+        });
+        match slot {
+            Empty => {
+                self.zip(path, other, true);
+                Ok(())
+            }
+            blocked => {
+                self.zip(path, blocked, false);
+                Err(other)
+            }
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Sum};
+
+#[cfg(test)]
+fn in_order(tree: &BST<i32, Sum<i32>>) -> Vec<i32> {
+    tree.iter_in_order().cloned().collect()
+}
+
+#[test]
+fn subtree_at_test() {
+    let tree: BST<i32, Sum<i32>> = sample_tree();
+    let sub = tree.subtree_at(&13).unwrap();
+    assert_eq!(in_order(sub), vec![10, 13, 20]);
+    assert_eq!(sub.summary(), Sum(43));
+    assert_eq!(tree.subtree_at(&8).unwrap().size(), 7);
+    assert!(tree.subtree_at(&9).is_none());
+}
+
+#[test]
+fn detach_subtree_test() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    let sub = tree.detach_subtree(&6).unwrap();
+    assert_eq!(in_order(&sub), vec![1, 6, 7]);
+    assert_eq!(sub.summary(), Sum(14));
+    assert_eq!(in_order(&tree), vec![8, 10, 13, 20]);
+    assert_eq!(tree.summary(), Sum(51));
+
+    assert!(tree.detach_subtree(&6).is_none());
+    assert_eq!(tree.summary(), Sum(51));
+
+    let all = tree.detach_subtree(&8).unwrap();
+    assert_eq!(all.size(), 4);
+    assert_eq!(tree.size(), 0);
+}
+
+#[test]
+fn graft_into_empty_slot() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    let sub = tree.detach_subtree(&6).unwrap();
+    assert!(tree.graft(sub).is_ok());
+    assert_eq!(in_order(&tree), vec![1, 6, 7, 8, 10, 13, 20]);
+    assert_eq!(tree.summary(), Sum(65));
+
+    // 14..=16 fits between 13 and 20, below 20 on the left
+    let extra: BST<i32, Sum<i32>> = vec![14, 15, 16].into_iter().collect();
+    assert!(tree.graft(extra).is_ok());
+    assert_eq!(tree.path_to(&15).unwrap().len(), 3);
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.aggregate(14..17), Sum(45));
+
+    let mut empty: BST<i32, Sum<i32>> = BST::new();
+    assert!(empty.graft(sample_tree()).is_ok());
+    assert_eq!(empty.size(), 7);
+}
+
+#[test]
+fn graft_rejects_overlapping_ranges() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    // 9..=11 straddles 10
+    let overlapping: BST<i32, Sum<i32>> = vec![9, 11].into_iter().collect();
+    let back = tree.graft(overlapping).unwrap_err();
+    assert_eq!(in_order(&back), vec![9, 11]);
+    assert_eq!(in_order(&tree), vec![1, 6, 7, 8, 10, 13, 20]);
+    assert_eq!(tree.summary(), Sum(65));
+
+    // an out of order tree is refused even if its range fits
+    let mut broken: BST<i32, Sum<i32>> = vec![2, 3, 4].into_iter().collect();
+    broken.swap_left();
+    assert!(tree.graft(broken).is_err());
+    assert_eq!(tree.validate(), Ok(()));
+}
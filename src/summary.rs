@@ -0,0 +1,404 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Per-node summaries for aggregating values over subtrees and ranges
+//
+//
+
+use std::cmp;
+use std::fmt::Debug;
+use std::ops::{Add, RangeBounds};
+
+use super::BST::*;
+use super::{above_start, below_end, BST};
+
+///
+/// A monoid summarising the values of a subtree.
+///
+/// Every BSTNode keeps the summary of its whole subtree, recomputed from its
+/// children whenever the tree changes shape. `combine` must be associative and
+/// `identity` must leave any summary unchanged; values are always combined in
+/// order, so the operation does not have to be commutative.
+///
+pub trait Summary<V>: Clone {
+    fn identity() -> Self;
+    fn summarize(val: &V) -> Self;
+    fn combine(&self, other: &Self) -> Self;
+}
+
+///
+/// The empty summary used by a plain `BST<V>`.
+///
+impl<V> Summary<V> for () {
+    fn identity() {}
+    fn summarize(_val: &V) {}
+    fn combine(&self, _other: &()) {}
+}
+
+///
+/// Number of values.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Count(pub usize);
+
+impl<V> Summary<V> for Count {
+    fn identity() -> Self {
+        Count(0)
+    }
+
+    fn summarize(_val: &V) -> Self {
+        Count(1)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Count(self.0 + other.0)
+    }
+}
+
+///
+/// Sum of values, starting from `V::default()`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sum<V>(pub V);
+
+impl<V> Summary<V> for Sum<V>
+where
+    V: Copy + Default + Add<Output = V>,
+{
+    fn identity() -> Self {
+        Sum(V::default())
+    }
+
+    fn summarize(val: &V) -> Self {
+        Sum(*val)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Sum(self.0 + other.0)
+    }
+}
+
+///
+/// Smallest value, None for no values.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Min<V>(pub Option<V>);
+
+impl<V> Summary<V> for Min<V>
+where
+    V: Copy + Ord,
+{
+    fn identity() -> Self {
+        Min(None)
+    }
+
+    fn summarize(val: &V) -> Self {
+        Min(Some(*val))
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Min(Some(cmp::min(a, b))),
+            (a, None) => Min(a),
+            (None, b) => Min(b),
+        }
+    }
+}
+
+///
+/// Largest value, None for no values.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Max<V>(pub Option<V>);
+
+impl<V> Summary<V> for Max<V>
+where
+    V: Copy + Ord,
+{
+    fn identity() -> Self {
+        Max(None)
+    }
+
+    fn summarize(val: &V) -> Self {
+        Max(Some(*val))
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Max(Some(cmp::max(a, b))),
+            (a, None) => Max(a),
+            (None, b) => Max(b),
+        }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Combines, in order, the summaries of every value inside `range`.
+    ///
+    /// Subtrees that lie completely inside the range contribute their stored
+    /// summary, so only the two boundary paths are walked, costing O(height).
+    /// `insert` does not rebalance, so that is O(log n) only for trees built with
+    /// `collect` or balanced again by `rebuild`; values inserted in sorted order
+    /// leave a chain and make it O(n).
+    ///
+    pub fn aggregate<R: RangeBounds<V>>(&self, range: R) -> S {
+        // Down to the first node inside the range, where the two boundaries part
+        let mut tree = self;
+        let split = loop {
+            let n = match *tree {
+                Empty => return S::identity(),
+                NonEmpty(ref n) => n,
+            };
+            tree = match n.val {
+                Some(ref v) if !above_start(&range, v) => &n.right,
+                Some(ref v) if !below_end(&range, v) => &n.left,
+                Some(_) => break n,
+                None => return S::identity(),
+            };
+        };
+
+        // Left boundary: every value is below the end, collect those from the start
+        let mut low = S::identity();
+        let mut tree = &split.left;
+        while let NonEmpty(ref n) = *tree {
+            tree = match n.val {
+                Some(ref v) if above_start(&range, v) => {
+                    low = S::summarize(v).combine(&n.right.summary()).combine(&low);
+                    &n.left
+                }
+                Some(_) => &n.right,
+                None => break,
+            };
+        }
+
+        // Right boundary: every value is above the start, collect those to the end
+        let mut high = S::identity();
+        let mut tree = &split.right;
+        while let NonEmpty(ref n) = *tree {
+            tree = match n.val {
+                Some(ref v) if below_end(&range, v) => {
+                    high = high.combine(&n.left.summary()).combine(&S::summarize(v));
+                    &n.right
+                }
+                Some(_) => &n.left,
+                None => break,
+            };
+        }
+
+        let own = split.val.as_ref().map_or_else(S::identity, S::summarize);
+        low.combine(&own).combine(&high)
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use std::cell::Cell;
+
+#[cfg(test)]
+use super::{sample_tree, BSTNode};
+
+#[cfg(test)]
+fn brute_sum(values: &[i64], lo: i64, hi: i64) -> i64 {
+    values.iter().filter(|v| **v >= lo && **v < hi).sum()
+}
+
+#[test]
+fn summary_sum_over_ranges() {
+    let values: Vec<i64> = vec![8, 13, 6, 1, 20, 10, 7, 19, 3, 15];
+    let mut tree: BST<i64, Sum<i64>> = BST::new();
+    for v in &values {
+        tree.insert(*v);
+    }
+    assert_eq!(tree.summary(), Sum(102));
+    assert_eq!(tree.aggregate(..), Sum(102));
+    for lo in 0..22 {
+        for hi in lo..22 {
+            assert_eq!(tree.aggregate(lo..hi), Sum(brute_sum(&values, lo, hi)));
+        }
+    }
+    assert_eq!(tree.aggregate(6..=13), Sum(6 + 7 + 8 + 10 + 13));
+    assert_eq!(tree.aggregate(..7), Sum(1 + 3 + 6));
+    assert_eq!(tree.aggregate(15..), Sum(15 + 19 + 20));
+}
+
+#[test]
+fn summary_min_max_count() {
+    let count: BST<i32, Count> = sample_tree();
+    let min: BST<i32, Min<i32>> = sample_tree();
+    let max: BST<i32, Max<i32>> = sample_tree();
+    assert_eq!(count.summary(), Count(7));
+    assert_eq!(count.aggregate(5..11), Count(4));
+    assert_eq!(min.aggregate(7..), Min(Some(7)));
+    assert_eq!(min.aggregate(21..), Min(None));
+    assert_eq!(max.aggregate(..13), Max(Some(10)));
+    assert_eq!(max.summary(), Max(Some(20)));
+}
+
+// Keeps the values in order, so any mix-up of the combine order shows
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+struct Seq(Vec<i32>);
+
+#[cfg(test)]
+impl Summary<i32> for Seq {
+    fn identity() -> Self {
+        Seq(Vec::new())
+    }
+
+    fn summarize(val: &i32) -> Self {
+        Seq(vec![*val])
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Seq(self.0.iter().chain(other.0.iter()).cloned().collect())
+    }
+}
+
+#[test]
+fn summary_aggregate_keeps_order() {
+    let values = [8, 13, 6, 1, 20, 10, 7, 19, 3, 15, 11, 2];
+    let mut tree: BST<i32, Seq> = BST::new();
+    for v in &values {
+        tree.insert(*v);
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    for lo in 0..22 {
+        for hi in lo..22 {
+            let expected: Vec<i32> = sorted
+                .iter()
+                .cloned()
+                .filter(|v| *v >= lo && *v <= hi)
+                .collect();
+            assert_eq!(tree.aggregate(lo..=hi), Seq(expected));
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static COMBINES: Cell<usize> = const { Cell::new(0) };
+}
+
+// A sum that counts its combine calls, to see how much of the tree a query touches
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Counted(u64);
+
+#[cfg(test)]
+impl Summary<u64> for Counted {
+    fn identity() -> Self {
+        Counted(0)
+    }
+
+    fn summarize(val: &u64) -> Self {
+        Counted(*val)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        COMBINES.with(|c| c.set(c.get() + 1));
+        Counted(self.0 + other.0)
+    }
+}
+
+// Combines done by one aggregate call
+#[cfg(test)]
+fn combines_for(tree: &BST<u64, Counted>, lo: u64, hi: u64) -> usize {
+    COMBINES.with(|c| c.set(0));
+    assert_eq!(tree.aggregate(lo..hi), Counted((lo..hi).sum()));
+    COMBINES.with(|c| c.get())
+}
+
+#[test]
+fn summary_aggregate_is_logarithmic_when_balanced() {
+    let n = 4096;
+    let collected: BST<u64, Counted> = (0..n).collect();
+    assert_eq!(collected.height(), 13);
+    for &(lo, hi) in &[(0, n), (1, n - 1), (100, 3000), (2047, 2049)] {
+        // at most two combines per level on each boundary, and two at the split
+        assert!(combines_for(&collected, lo, hi) <= 4 * 13 + 2);
+    }
+
+    // sorted inserts leave a chain, which rebuild balances again
+    let mut chain: BST<u64, Counted> = BST::new();
+    for v in 0..n / 8 {
+        chain.insert(v);
+    }
+    assert!(combines_for(&chain, 1, n / 8 - 1) > n as usize / 8);
+    chain.rebuild();
+    assert!(combines_for(&chain, 1, n / 8 - 1) <= 4 * chain.height() as usize + 2);
+}
+
+#[test]
+fn summary_aggregate_deep_chain() {
+    // a sorted build is one long chain, the walk must not recurse
+    let mut tree: BST<u64, Sum<u64>> = BST::new();
+    for v in (0..100_000u64).rev() {
+        tree = NonEmpty(Box::new(BSTNode {
+            summary: tree.summary().combine(&Sum(v)),
+            val: Some(v),
+            left: Empty,
+            right: tree,
+            depth: 1,
+        }));
+    }
+    assert_eq!(tree.aggregate(10..20), Sum(145));
+    assert_eq!(tree.aggregate(99_990..), Sum((99_990..100_000).sum()));
+}
+
+#[test]
+fn summary_empty_tree() {
+    let tree: BST<i32, Sum<i32>> = BST::new();
+    assert_eq!(tree.summary(), Sum(0));
+    assert_eq!(tree.aggregate(1..5), Sum(0));
+}
+
+#[test]
+fn summary_follows_structural_changes() {
+    let mut tree: BST<i32, Count> = sample_tree();
+
+    let left = tree.take_left().unwrap();
+    assert_eq!(left.summary(), Count(3));
+    assert_eq!(tree.summary(), Count(4));
+
+    let right = tree.take_right().unwrap();
+    assert_eq!(right.summary(), Count(3));
+    assert_eq!(tree.summary(), Count(1));
+}
+
+#[test]
+fn summary_follows_swaps() {
+    // Min depends on which values sit where, so a swap must refresh both nodes
+    let mut tree: BST<i32, Min<i32>> = sample_tree();
+    tree.swap_right();
+    if let NonEmpty(ref n) = tree {
+        assert_eq!(n.right.summary(), Min(Some(8)));
+    }
+    assert_eq!(tree.summary(), Min(Some(1)));
+}
+
+#[test]
+fn summary_follows_cursor_edits() {
+    let mut tree: BST<i32, Sum<i32>> = sample_tree();
+    {
+        let mut cursor = tree.cursor_mut_at(&7).unwrap();
+        assert_eq!(cursor.insert_after(7), Err(7));
+        assert_eq!(cursor.remove_current(), Some(7));
+        cursor.move_next();
+        // on 10 now, replace it by 11
+        assert_eq!(cursor.current(), Some(&10));
+        cursor.insert_after(11).unwrap();
+        assert_eq!(cursor.remove_current(), Some(10));
+        // 8 has two children, removal pulls its successor up
+        cursor.move_prev();
+        assert_eq!(cursor.remove_current(), Some(8));
+    }
+    assert_eq!(tree.iter_in_order().cloned().collect::<Vec<i32>>(), vec![1, 6, 11, 13, 20]);
+    assert_eq!(tree.summary(), Sum(51));
+    assert_eq!(tree.aggregate(2..12), Sum(17));
+}
@@ -0,0 +1,209 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Shape preserving map, bottom-up fold and mirror image of a tree
+//
+//
+
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Summary, BST};
+
+enum Frame<'a, V: 'a, S: 'a> {
+    Enter(&'a BST<V, S>),
+    Combine(&'a BSTNode<V, S>),
+}
+
+// Post-order over every node with an explicit stack. `empty` stands in for each
+// missing subtree; `node` gets a node with the results of its left and right.
+fn fold_nodes<V, S, A, E, N>(tree: &BST<V, S>, mut empty: E, mut node: N) -> A
+where
+    E: FnMut() -> A,
+    N: FnMut(&BSTNode<V, S>, A, A) -> A,
+{
+    let mut stack = vec![Frame::Enter(tree)];
+    let mut results = Vec::new();
+    while let Some(frame) = stack.pop() {
+        match frame {
+            Frame::Enter(&Empty) => results.push(empty()),
+            Frame::Enter(NonEmpty(n)) => {
+                stack.push(Frame::Combine(n));
+                stack.push(Frame::Enter(&n.right));
+                stack.push(Frame::Enter(&n.left));
+            }
+            Frame::Combine(n) => {
+                let right = results.pop().unwrap();
+                let left = results.pop().unwrap();
+                results.push(node(n, left, right));
+            }
+        }
+    }
+    results.pop().unwrap()
+}
+
+fn join<V, S>(val: Option<V>, left: BST<V, S>, right: BST<V, S>) -> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    let mut node = BSTNode {
+        val,
+        left,
+        right,
+        depth: 1,
+        summary: S::identity(),
+    };
+    node.update_summary();
+    NonEmpty(Box::new(node))
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Builds a tree of the same shape with `f` applied to every value, node for
+    /// node, with summaries of the new type computed along the way.
+    ///
+    /// The result is only a valid search tree if `f` is strictly increasing; use
+    /// `validate` or `repair` otherwise.
+    ///
+    pub fn map_preserving_shape<U, T, F>(&self, mut f: F) -> BST<U, T>
+    where
+        U: Debug + Copy + Clone + Ord + PartialEq,
+        T: Summary<U>,
+        F: FnMut(&V) -> U,
+    {
+        fold_nodes(
+            self,
+            || Empty,
+            |n, left, right| join(n.val.as_ref().map(&mut f), left, right),
+        )
+    }
+
+    ///
+    /// Folds the tree bottom-up. A node without children becomes `leaf_fn(val)`;
+    /// any other node becomes `combine_fn(left, val, right)` from the results of
+    /// its subtrees, a missing subtree counting as `init`. An empty tree folds to
+    /// `init`.
+    ///
+    /// Nodes that lost their value to a swap fold to `init`, with everything below.
+    ///
+    pub fn fold_post_order<A, L, C>(&self, init: A, mut leaf_fn: L, mut combine_fn: C) -> A
+    where
+        A: Clone,
+        L: FnMut(&V) -> A,
+        C: FnMut(A, &V, A) -> A,
+    {
+        fold_nodes(
+            self,
+            || init.clone(),
+            |n, left, right| match n.val {
+                None => init.clone(),
+                Some(ref v) => match (&n.left, &n.right) {
+                    (&Empty, &Empty) => leaf_fn(v),
+                    _ => combine_fn(left, v, right),
+                },
+            },
+        )
+    }
+
+    ///
+    /// Returns the mirror image, every left and right child exchanged, so the
+    /// values read back to front in order.
+    ///
+    /// The mirror is ordered the opposite way, which `find` and `insert` do not
+    /// expect; mirroring it again gives back the original tree.
+    ///
+    pub fn mirror(&self) -> BST<V, S> {
+        fold_nodes(self, || Empty, |n, left, right| join(n.val, right, left))
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::{sample_tree, Count, Sum};
+
+#[test]
+fn map_keeps_shape() {
+    let tree: BST<i32, Sum<i32>> = sample_tree();
+    let doubled: BST<i64, Sum<i64>> = tree.map_preserving_shape(|v| i64::from(*v) * 2);
+    assert_eq!(
+        doubled.iter_pre_order().cloned().collect::<Vec<i64>>(),
+        vec![16, 12, 2, 14, 26, 20, 40]
+    );
+    assert_eq!(doubled.summary(), Sum(130));
+    assert_eq!(doubled.aggregate(10..21), Sum(62));
+
+    // a decreasing map keeps the shape but not the order
+    let negated: BST<i32, Count> = tree.map_preserving_shape(|v| -v);
+    assert_eq!(negated.summary(), Count(7));
+    assert!(negated.validate().is_err());
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.map_preserving_shape::<i32, (), _>(|v| *v).size(), 0);
+}
+
+#[test]
+fn fold_subtree_sums_and_heights() {
+    let tree: BST<i32, Sum<i32>> = sample_tree();
+    let sum = tree.fold_post_order(0, |v| *v, |l, v, r| l + v + r);
+    assert_eq!(sum, 65);
+    let height = tree.fold_post_order(0u64, |_| 1, |l, _, r| 1 + l.max(r));
+    assert_eq!(height, tree.height());
+
+    // results combine left, node, right: collecting children first gives post-order
+    let post = tree.fold_post_order(
+        vec![],
+        |v| vec![*v],
+        |mut l, v, r| {
+            l.extend(r);
+            l.push(*v);
+            l
+        },
+    );
+    assert_eq!(post, tree.iter_post_order().cloned().collect::<Vec<i32>>());
+    let leaves = tree.fold_post_order(0, |_| 1, |l, _, r| l + r);
+    assert_eq!(leaves, 4);
+
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.fold_post_order(-1, |_| 1, |l, _, r| l + r), -1);
+}
+
+#[test]
+fn mirror_reverses_order() {
+    let tree: BST<i32, Sum<i32>> = sample_tree();
+    let mirrored = tree.mirror();
+    assert_eq!(
+        mirrored.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![20, 13, 10, 8, 7, 6, 1]
+    );
+    assert_eq!(mirrored.summary(), Sum(65));
+    assert!(mirrored.validate().is_err());
+    assert_eq!(
+        mirrored
+            .mirror()
+            .iter_pre_order()
+            .cloned()
+            .collect::<Vec<i32>>(),
+        tree.iter_pre_order().cloned().collect::<Vec<i32>>()
+    );
+}
+
+#[test]
+fn transforms_deep_chain() {
+    let mut tree: BST<u32> = BST::new();
+    for v in 0..5000 {
+        tree.insert(v);
+    }
+    let count: BST<u32, Count> = tree.map_preserving_shape(|v| v + 1);
+    assert_eq!(count.summary(), Count(5000));
+    assert_eq!(count.height(), 5000);
+    assert_eq!(tree.mirror().iter_in_order().next(), Some(&4999));
+    assert_eq!(
+        tree.fold_post_order(0u64, |_| 1, |l, _, r| 1 + l.max(r)),
+        5000
+    );
+}
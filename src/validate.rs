@@ -0,0 +1,239 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Checking and restoring the search tree ordering
+//
+//
+
+use std::fmt;
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Direction, Summary, BST};
+
+///
+/// The first node found breaking the search tree invariant.
+///
+/// `path` leads from the root to the node. Every value in the tree must lie strictly
+/// between the nearest ancestor it sits to the right of (`lower`) and the nearest
+/// ancestor it sits to the left of (`upper`). `value` is None for a node that has
+/// lost its value, which `swap_left`/`swap_right` do when the child is missing.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation<V> {
+    pub path: Vec<Direction>,
+    pub value: Option<V>,
+    pub lower: Option<V>,
+    pub upper: Option<V>,
+}
+
+impl<V: Debug> fmt::Display for InvariantViolation<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node at root")?;
+        for d in &self.path {
+            match *d {
+                Direction::Left => write!(f, "/L")?,
+                Direction::Right => write!(f, "/R")?,
+            }
+        }
+        match self.value {
+            None => write!(f, " has no value"),
+            Some(ref v) => write!(
+                f,
+                " holds {:?} outside of ({:?}, {:?})",
+                v, self.lower, self.upper
+            ),
+        }
+    }
+}
+
+// A node to check with the bounds its ancestors put on it
+struct Check<'a, V: 'a, S: 'a> {
+    node: &'a BSTNode<V, S>,
+    depth: usize,
+    side: Option<Direction>,
+    lower: Option<V>,
+    upper: Option<V>,
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Checks that an in-order walk is strictly increasing, so searches can be trusted.
+    ///
+    /// Returns the first offending node in pre-order.
+    ///
+    pub fn validate(&self) -> Result<(), InvariantViolation<V>> {
+        let mut path: Vec<Direction> = Vec::new();
+        let mut stack = match *self {
+            Empty => Vec::new(),
+            NonEmpty(ref n) => vec![Check {
+                node: &**n,
+                depth: 0,
+                side: None,
+                lower: None,
+                upper: None,
+            }],
+        };
+        while let Some(c) = stack.pop() {
+            // Rewind the path to this node's parent, then step into the node
+            path.truncate(c.depth.saturating_sub(1));
+            if let Some(side) = c.side {
+                path.push(side);
+            }
+            let v = match c.node.val {
+                Some(v) if c.lower.is_none_or(|lo| v > lo) && c.upper.is_none_or(|hi| v < hi) => v,
+                value => {
+                    return Err(InvariantViolation {
+                        path,
+                        value,
+                        lower: c.lower,
+                        upper: c.upper,
+                    })
+                }
+            };
+            if let NonEmpty(ref r) = c.node.right {
+                stack.push(Check {
+                    node: &**r,
+                    depth: c.depth + 1,
+                    side: Some(Direction::Right),
+                    lower: Some(v),
+                    upper: c.upper,
+                });
+            }
+            if let NonEmpty(ref l) = c.node.left {
+                stack.push(Check {
+                    node: &**l,
+                    depth: c.depth + 1,
+                    side: Some(Direction::Left),
+                    lower: c.lower,
+                    upper: Some(v),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Rebuilds the tree as a balanced BST of its current values.
+    ///
+    /// Duplicates left behind by swaps are kept once and nodes without a value are
+    /// dropped.
+    ///
+    pub fn rebuild(&mut self) {
+        let mut values = Vec::new();
+        let mut stack = vec![&*self];
+        while let Some(tree) = stack.pop() {
+            if let NonEmpty(ref n) = *tree {
+                if let Some(v) = n.val {
+                    values.push(v);
+                }
+                stack.push(&n.left);
+                stack.push(&n.right);
+            }
+        }
+        values.sort();
+        values.dedup();
+        *self = BST::from_sorted(&values);
+    }
+
+    ///
+    /// Rebuilds the tree only if `validate` fails. Returns true if it was rebuilt.
+    ///
+    pub fn repair(&mut self) -> bool {
+        if self.validate().is_ok() {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+#[test]
+fn validate_accepts_inserted_trees() {
+    let tree: BST<i32> = sample_tree();
+    assert_eq!(tree.validate(), Ok(()));
+    let empty: BST<i32> = BST::new();
+    assert_eq!(empty.validate(), Ok(()));
+}
+
+#[test]
+fn validate_reports_swapped_nodes() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.swap_right();
+    // 13 and 8 traded places: 8 now sits to the right of 13
+    assert_eq!(
+        tree.validate(),
+        Err(InvariantViolation {
+            path: vec![Direction::Right],
+            value: Some(8),
+            lower: Some(13),
+            upper: None,
+        })
+    );
+    assert_eq!(
+        format!("{}", tree.validate().unwrap_err()),
+        "node at root/R holds 8 outside of (Some(13), None)"
+    );
+
+    let mut tree: BST<i32> = sample_tree();
+    if let NonEmpty(ref mut n) = tree {
+        n.left.swap_right();
+    }
+    // 6 and 7 traded places, 6 is still below 8 but now right of 7
+    let err = tree.validate().unwrap_err();
+    assert_eq!(err.path, vec![Direction::Left, Direction::Right]);
+    assert_eq!(err.value, Some(6));
+}
+
+#[test]
+fn validate_reports_lost_values() {
+    let mut tree: BST<i32> = sample_tree();
+    if let NonEmpty(ref mut n) = tree {
+        // 6 moves down into the leaf, which has no right child for the next swap
+        n.left.swap_left();
+        if let NonEmpty(ref mut l) = n.left {
+            l.left.swap_right();
+        }
+    }
+    let err = tree.validate().unwrap_err();
+    assert_eq!(err.path, vec![Direction::Left, Direction::Left]);
+    assert_eq!(err.value, None);
+}
+
+#[test]
+fn repair_restores_searches() {
+    let mut tree: BST<i32> = sample_tree();
+    tree.swap_right();
+    assert!(!tree.contains(8));
+
+    assert!(tree.repair());
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.contains(8));
+    assert_eq!(
+        tree.iter_in_order().cloned().collect::<Vec<i32>>(),
+        vec![1, 6, 7, 8, 10, 13, 20]
+    );
+    assert_eq!(tree.height(), 3);
+    assert!(!tree.repair());
+}
+
+#[test]
+fn rebuild_balances_valid_trees() {
+    let mut tree: BST<u32> = BST::new();
+    for v in 0..100 {
+        tree.insert(v);
+    }
+    assert_eq!(tree.height(), 100);
+    tree.rebuild();
+    assert_eq!(tree.height(), 7);
+    assert_eq!(tree.size(), 100);
+    assert_eq!(tree.validate(), Ok(()));
+}
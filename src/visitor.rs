@@ -0,0 +1,225 @@
+// Copyright (c) 2018 Jason Graalum & Nathan Reed
+// Visitor driven traversal with depth, pruning and early exit
+//
+//
+
+use std::fmt::Debug;
+
+use super::BST::*;
+use super::{BSTNode, Direction, Summary, BST};
+
+///
+/// What the walker should do after a callback.
+///
+/// `SkipChildren` only has an effect when returned from `enter_node`: the node's
+/// subtrees are not walked, although its own `visit_in_order` and `exit_node`
+/// still run. Elsewhere it behaves like `Continue`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitControl {
+    Continue,
+    SkipChildren,
+    Stop,
+}
+
+///
+/// Callbacks for `BST::walk`.
+///
+/// For every node the walker calls `enter_node`, walks the left subtree, calls
+/// `visit_in_order`, walks the right subtree and finally calls `exit_node`, so the
+/// three callbacks see the values in pre-, in- and post-order. `visit_empty` is
+/// called for every missing child with the side it hangs on, None for an empty tree.
+/// Depths count nodes, the root is at depth 1.
+///
+/// Every callback has a default that does nothing and continues.
+///
+pub trait TreeVisitor<V> {
+    fn enter_node(&mut self, _depth: usize, _val: &V) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_in_order(&mut self, _depth: usize, _val: &V) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn exit_node(&mut self, _depth: usize, _val: &V) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_empty(&mut self, _depth: usize, _side: Option<Direction>) {}
+}
+
+enum Frame<'a, V: 'a, S: 'a> {
+    Enter(&'a BST<V, S>, usize, Option<Direction>),
+    InOrder(&'a BSTNode<V, S>, usize),
+    Exit(&'a BSTNode<V, S>, usize),
+}
+
+//++++++++++++++++++++++++++++++++++IMPL-BST+++++++++++++++++++++++++++++++++++++
+impl<V, S> BST<V, S>
+where
+    V: Debug + Copy + Clone + Ord + PartialEq,
+    S: Summary<V>,
+{
+    ///
+    /// Walks the tree depth first, driving `visitor`. Uses an explicit stack, so
+    /// degenerate trees are fine. Returns false if a callback stopped the walk.
+    ///
+    /// Nodes that lost their value to a swap get no callbacks, but their subtrees
+    /// are still walked.
+    ///
+    pub fn walk<T: TreeVisitor<V>>(&self, visitor: &mut T) -> bool {
+        let mut stack = vec![Frame::Enter(self, 1, None)];
+        while let Some(frame) = stack.pop() {
+            let control = match frame {
+                Frame::Enter(&Empty, depth, side) => {
+                    visitor.visit_empty(depth, side);
+                    VisitControl::Continue
+                }
+                Frame::Enter(NonEmpty(n), depth, _) => {
+                    let control = match n.val {
+                        Some(ref v) => visitor.enter_node(depth, v),
+                        None => VisitControl::Continue,
+                    };
+                    stack.push(Frame::Exit(n, depth));
+                    if control == VisitControl::SkipChildren {
+                        stack.push(Frame::InOrder(n, depth));
+                    } else {
+                        stack.push(Frame::Enter(&n.right, depth + 1, Some(Direction::Right)));
+                        stack.push(Frame::InOrder(n, depth));
+                        stack.push(Frame::Enter(&n.left, depth + 1, Some(Direction::Left)));
+                    }
+                    control
+                }
+                Frame::InOrder(n, depth) => match n.val {
+                    Some(ref v) => visitor.visit_in_order(depth, v),
+                    None => VisitControl::Continue,
+                },
+                Frame::Exit(n, depth) => match n.val {
+                    Some(ref v) => visitor.exit_node(depth, v),
+                    None => VisitControl::Continue,
+                },
+            };
+            if control == VisitControl::Stop {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+//++++++++++++++++++++++++++++++++++TEST+++++++++++++++++++++++++++++++++++++
+
+#[cfg(test)]
+use super::sample_tree;
+
+// Records every callback in order
+#[cfg(test)]
+#[derive(Default)]
+struct Recorder {
+    pre: Vec<(usize, i32)>,
+    in_order: Vec<i32>,
+    post: Vec<i32>,
+    empty: Vec<(usize, Option<Direction>)>,
+    skip: Option<i32>,
+    stop_after: Option<i32>,
+}
+
+#[cfg(test)]
+impl TreeVisitor<i32> for Recorder {
+    fn enter_node(&mut self, depth: usize, val: &i32) -> VisitControl {
+        self.pre.push((depth, *val));
+        if self.skip == Some(*val) {
+            VisitControl::SkipChildren
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn visit_in_order(&mut self, _depth: usize, val: &i32) -> VisitControl {
+        self.in_order.push(*val);
+        if self.stop_after == Some(*val) {
+            VisitControl::Stop
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn exit_node(&mut self, _depth: usize, val: &i32) -> VisitControl {
+        self.post.push(*val);
+        VisitControl::Continue
+    }
+
+    fn visit_empty(&mut self, depth: usize, side: Option<Direction>) {
+        self.empty.push((depth, side));
+    }
+}
+
+#[test]
+fn walk_matches_iterators() {
+    let tree: BST<i32> = sample_tree();
+    let mut rec = Recorder::default();
+    assert!(tree.walk(&mut rec));
+    assert_eq!(
+        rec.pre,
+        vec![(1, 8), (2, 6), (3, 1), (3, 7), (2, 13), (3, 10), (3, 20)]
+    );
+    assert_eq!(
+        rec.in_order,
+        tree.iter_in_order().cloned().collect::<Vec<i32>>()
+    );
+    assert_eq!(
+        rec.post,
+        tree.iter_post_order().cloned().collect::<Vec<i32>>()
+    );
+    assert_eq!(rec.empty.len(), 8);
+    assert_eq!(rec.empty[0], (4, Some(Direction::Left)));
+
+    let empty: BST<i32> = BST::new();
+    let mut rec = Recorder::default();
+    assert!(empty.walk(&mut rec));
+    assert_eq!(rec.empty, vec![(1, None)]);
+}
+
+#[test]
+fn walk_skips_children() {
+    let tree: BST<i32> = sample_tree();
+    let mut rec = Recorder {
+        skip: Some(6),
+        ..Recorder::default()
+    };
+    assert!(tree.walk(&mut rec));
+    assert_eq!(rec.in_order, vec![6, 8, 10, 13, 20]);
+    assert_eq!(rec.post, vec![6, 10, 20, 13, 8]);
+}
+
+#[test]
+fn walk_stops_early() {
+    let tree: BST<i32> = sample_tree();
+    let mut rec = Recorder {
+        stop_after: Some(7),
+        ..Recorder::default()
+    };
+    assert!(!tree.walk(&mut rec));
+    assert_eq!(rec.in_order, vec![1, 6, 7]);
+    assert_eq!(rec.post, vec![1]);
+}
+
+#[test]
+fn walk_deep_chain() {
+    // Remembers the depth of the last node entered, the only callback it needs
+    struct Deepest(usize);
+    impl TreeVisitor<u32> for Deepest {
+        fn enter_node(&mut self, depth: usize, _val: &u32) -> VisitControl {
+            self.0 = depth;
+            VisitControl::Continue
+        }
+    }
+    let mut tree: BST<u32> = BST::new();
+    for v in 0..5000 {
+        tree.insert(v);
+    }
+    let mut deepest = Deepest(0);
+    assert!(tree.walk(&mut deepest));
+    assert_eq!(deepest.0, 5000);
+}